@@ -0,0 +1,176 @@
+// benches/solver.rs
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+use tf_solver::compile::Plan;
+use tf_solver::fit::{VARIABLE_SYMBOL, substitute, variable};
+use tf_solver::generator::{GenConfig, generate_valid_tokens, generate_valid_tokens_of_length};
+use tf_solver::opes_data::prepare_opes;
+use tf_solver::parser::parse_rpn_with;
+use tf_solver::rpn::calculate;
+use tf_solver::types::{BinaryOperator, Operand, Token, UnaryOperator};
+
+// 完整的运算符表在深度4以上规模过大，生成相关的基准只使用其中一部分
+const UNARY_SUBSET: [&str; 3] = ["sin", "sqrt", "ln"];
+const BINARY_SUBSET: [&str; 4] = ["+", "-", "*", "/"];
+
+/// 从完整的运算符表中选出符号在 `unary` 和 `binary` 中的运算符
+fn reduced_opes(
+    unary: &[&str],
+    binary: &[&str],
+) -> (Vec<Operand>, Vec<UnaryOperator>, Vec<BinaryOperator>) {
+    let (operands, unary_operators, binary_operators) = prepare_opes();
+    (
+        operands,
+        unary_operators
+            .into_iter()
+            .filter(|op| unary.contains(&op.symbol.as_str()))
+            .collect(),
+        binary_operators
+            .into_iter()
+            .filter(|op| binary.contains(&op.symbol.as_str()))
+            .collect(),
+    )
+}
+
+/// 按深度生成token序列的吞吐量
+///
+/// 每个深度都允许同样多的一元运算符，即使只有几个一元运算符，深度6也有上千亿个序列，
+/// 因此这里只使用 `+` 和 `*`。
+fn bench_generate_by_depth(c: &mut Criterion) {
+    let (operands, unary_operators, binary_operators) = reduced_opes(&[], &["+", "*"]);
+    let mut group = c.benchmark_group("generate_valid_tokens");
+    group.sample_size(10);
+    for depth in 4..=6 {
+        let count = generate_valid_tokens(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            depth,
+            GenConfig::default(),
+        )
+        .count();
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, &depth| {
+            b.iter(|| {
+                generate_valid_tokens(
+                    &operands,
+                    &unary_operators,
+                    &binary_operators,
+                    black_box(depth),
+                    GenConfig::default(),
+                )
+                .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// 按总长度生成token序列的吞吐量
+fn bench_generate_by_length(c: &mut Criterion) {
+    let (operands, unary_operators, binary_operators) = reduced_opes(&UNARY_SUBSET, &BINARY_SUBSET);
+    let mut group = c.benchmark_group("generate_valid_tokens_of_length");
+    group.sample_size(10);
+    for length in [5, 7] {
+        let count = generate_valid_tokens_of_length(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            length,
+            GenConfig::default(),
+        )
+        .count();
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(length),
+            &length,
+            |b, &length| {
+                b.iter(|| {
+                    generate_valid_tokens_of_length(
+                        &operands,
+                        &unary_operators,
+                        &binary_operators,
+                        black_box(length),
+                        GenConfig::default(),
+                    )
+                    .count()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// 对一批固定的序列求值
+fn bench_calculate(c: &mut Criterion) {
+    let (operands, unary_operators, binary_operators) = prepare_opes();
+    let batch: Vec<Vec<Token>> = generate_valid_tokens(
+        &operands,
+        &unary_operators,
+        &binary_operators,
+        3,
+        GenConfig::default(),
+    )
+    .step_by(97)
+    .take(10_000)
+    .collect();
+
+    let mut group = c.benchmark_group("calculate");
+    group.throughput(Throughput::Elements(batch.len() as u64));
+    group.bench_function("batch", |b| {
+        b.iter(|| {
+            batch
+                .iter()
+                .map(|tokens| calculate(black_box(tokens)))
+                .filter(|value| value.is_finite())
+                .count()
+        })
+    });
+    group.finish();
+}
+
+/// 同一个序列在多个自变量取值下求值：逐次代入后 `calculate`，对比编译一次后反复求值
+fn bench_compile(c: &mut Criterion) {
+    let (_, unary_operators, binary_operators) = prepare_opes();
+    let tokens = parse_rpn_with(
+        "x sin x * 2 x ^ + x ln /",
+        &[variable()],
+        &unary_operators,
+        &binary_operators,
+    )
+    .unwrap();
+    let xs: Vec<f64> = (1..=1000).map(|i| i as f64 / 100.0).collect();
+
+    let mut group = c.benchmark_group("compile");
+    group.throughput(Throughput::Elements(xs.len() as u64));
+    group.bench_function("substitute_and_calculate", |b| {
+        b.iter(|| {
+            let mut tokens = tokens.clone();
+            xs.iter()
+                .map(|&x| {
+                    substitute(&mut tokens, VARIABLE_SYMBOL, black_box(x));
+                    calculate(&tokens)
+                })
+                .sum::<f64>()
+        })
+    });
+    group.bench_function("plan", |b| {
+        b.iter(|| {
+            let mut plan = Plan::new(&tokens, &[VARIABLE_SYMBOL]);
+            xs.iter()
+                .map(|&x| plan.evaluate(&[black_box(x)]))
+                .sum::<f64>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_generate_by_depth,
+    bench_generate_by_length,
+    bench_calculate,
+    bench_compile
+);
+criterion_main!(benches);
@@ -0,0 +1,257 @@
+// src/ast.rs
+use crate::opes_data::TRIVIAL_UNARY_PAIRS;
+use crate::rpn::calculate;
+use crate::types::{BinaryOperator, Operand, Token, TokenVec, UnaryOperator};
+
+// 折叠为整数字面量的上限，更大的整数写成字面量反而难读
+const MAX_FOLDED_INTEGER: f64 = 1e9;
+// 化简时视为相等的相对误差
+const RELATIVE_EPSILON: f64 = 1e-12;
+
+/// 表达式树，便于对表达式做变换；求值仍使用扁平的token序列
+#[derive(Clone)]
+pub enum Expr {
+    Const(Operand),                               // 操作数
+    Unary(UnaryOperator, Box<Expr>),              // 一元运算符及其参数
+    Binary(BinaryOperator, Box<Expr>, Box<Expr>), // 二元运算符及其左右参数
+}
+
+impl Expr {
+    /// 由RPN序列构造表达式树，序列无效时返回 `None`
+    pub fn from_rpn(tokens: &[Token]) -> Option<Expr> {
+        let mut stack: Vec<Expr> = Vec::new();
+        for token in tokens {
+            let expr = match token {
+                Token::Operand(operand) => Expr::Const(operand.clone()),
+                Token::UnaryOperator(operator) => {
+                    let value = stack.pop()?;
+                    Expr::Unary(operator.clone(), Box::new(value))
+                }
+                Token::BinaryOperator(operator) => {
+                    let right = stack.pop()?;
+                    let left = stack.pop()?;
+                    Expr::Binary(operator.clone(), Box::new(left), Box::new(right))
+                }
+            };
+            stack.push(expr);
+        }
+        // 有效的序列最终栈中恰好剩下一个表达式
+        let expr = stack.pop()?;
+        stack.is_empty().then_some(expr)
+    }
+
+    /// 按后序遍历把表达式树转换回RPN序列
+    pub fn to_rpn(&self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        self.push_rpn(&mut tokens);
+        tokens
+    }
+
+    /// 表达式的值，与对 `to_rpn` 的结果调用 `calculate` 相同
+    pub fn value(&self) -> f64 {
+        calculate(&self.to_rpn())
+    }
+
+    /// 化简为数值相同的更简洁的形式，用于展示匹配结果
+    ///
+    /// 自底向上依次应用以下规则：
+    /// - 值为整数的子表达式折叠为整数字面量，如 `e floor` -> `2`、`pi pi /` -> `1`，
+    ///   整个表达式本身除外；
+    /// - `TRIVIAL_UNARY_PAIRS` 中的组合去掉多余的一层，如 `sin(asin(x))` -> `x`、
+    ///   `abs(abs(x))` -> `abs(x)`；
+    /// - 恒等式 `x + 0`、`x - 0`、`x * 1`、`x / 1`、`x ^ 1`、`min(x, x)`、`max(x, x)` -> `x`。
+    ///
+    /// 所有叶子都是常数，每条规则只在改写前后子表达式的值在浮点误差内相等时才会应用。
+    /// 外层运算放大了这些误差时（如 `cot(tan(atan(pi)))`），退回到只应用值完全相同的改写，
+    /// 因此化简不会在浮点误差之外改变表达式的值（超出定义域的表达式保持 `NaN`）。
+    pub fn simplify(&self) -> Expr {
+        let simplified = self.simplify_within(RELATIVE_EPSILON, true);
+        if same_value(simplified.value(), self.value(), RELATIVE_EPSILON) {
+            simplified
+        } else {
+            self.simplify_within(0.0, true)
+        }
+    }
+
+    /// 自底向上化简，改写前后的值的相对误差不超过 `relative_epsilon` 时才应用改写
+    ///
+    /// 整个表达式（`root` 为真）不折叠为字面量，否则整数目标值的所有匹配结果都会变成同一个数字。
+    fn simplify_within(&self, relative_epsilon: f64, root: bool) -> Expr {
+        let simplified = match self {
+            Expr::Const(_) => return self.clone(),
+            Expr::Unary(operator, value) => Expr::Unary(
+                operator.clone(),
+                Box::new(value.simplify_within(relative_epsilon, false)),
+            ),
+            Expr::Binary(operator, left, right) => Expr::Binary(
+                operator.clone(),
+                Box::new(left.simplify_within(relative_epsilon, false)),
+                Box::new(right.simplify_within(relative_epsilon, false)),
+            ),
+        };
+        let value = simplified.value();
+        if !root && value.is_finite() && value.fract() == 0.0 && value.abs() <= MAX_FOLDED_INTEGER {
+            return Expr::Const(Operand {
+                symbol: format!("{}", value as i64),
+                value, // 保留 -0.0 的符号
+            });
+        }
+        simplified
+            .candidates()
+            .into_iter()
+            .find(|candidate| same_value(candidate.value(), value, relative_epsilon))
+            .unwrap_or(simplified)
+    }
+
+    /// 按 `simplify` 的规则可以替换当前表达式的更简单形式，尚未检查数值是否相同
+    fn candidates(&self) -> Vec<Expr> {
+        match self {
+            Expr::Const(_) => Vec::new(),
+            Expr::Unary(outer, value) => match value.as_ref() {
+                Expr::Unary(inner, argument)
+                    if TRIVIAL_UNARY_PAIRS
+                        .contains(&(inner.symbol.as_str(), outer.symbol.as_str())) =>
+                {
+                    // 外层多余时等于内层，互逆时等于内层的参数
+                    vec![value.as_ref().clone(), argument.as_ref().clone()]
+                }
+                _ => Vec::new(),
+            },
+            Expr::Binary(operator, left, right) => {
+                let is = |expr: &Expr, value: f64| matches!(expr, Expr::Const(operand) if operand.value == value);
+                let identity = match operator.symbol.as_str() {
+                    "+" if is(left, 0.0) => Some(right),
+                    "+" | "-" if is(right, 0.0) => Some(left),
+                    "*" if is(left, 1.0) => Some(right),
+                    "*" | "/" | "^" if is(right, 1.0) => Some(left),
+                    "min" | "max" if left.to_rpn_string() == right.to_rpn_string() => Some(left),
+                    _ => None,
+                };
+                identity
+                    .map(|expr| expr.as_ref().clone())
+                    .into_iter()
+                    .collect()
+            }
+        }
+    }
+
+    /// RPN形式的字符串，用于比较两个表达式是否相同
+    fn to_rpn_string(&self) -> String {
+        TokenVec(&self.to_rpn()).to_string()
+    }
+
+    fn push_rpn(&self, tokens: &mut Vec<Token>) {
+        match self {
+            Expr::Const(operand) => tokens.push(Token::Operand(operand.clone())),
+            Expr::Unary(operator, value) => {
+                value.push_rpn(tokens);
+                tokens.push(Token::UnaryOperator(operator.clone()));
+            }
+            Expr::Binary(operator, left, right) => {
+                left.push_rpn(tokens);
+                right.push_rpn(tokens);
+                tokens.push(Token::BinaryOperator(operator.clone()));
+            }
+        }
+    }
+}
+
+/// 两个值的相对误差不超过 `relative_epsilon`，或者都是 `NaN`
+///
+/// `relative_epsilon` 为0时要求两个值的二进制表示完全相同，以区分 `0.0` 和 `-0.0`。
+fn same_value(a: f64, b: f64, relative_epsilon: f64) -> bool {
+    a.to_bits() == b.to_bits()
+        || (a.is_nan() && b.is_nan())
+        || (relative_epsilon > 0.0 && (a - b).abs() <= relative_epsilon * a.abs().max(b.abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{GenConfig, generate_valid_tokens_of_length};
+    use crate::opes_data::prepare_opes;
+    use crate::parser::parse_rpn;
+    use crate::rpn::calculate;
+
+    #[test]
+    fn test_round_trip() {
+        let tokens = parse_rpn("pi sqrt e 2 ^ + γ sin /").unwrap();
+        let expr = Expr::from_rpn(&tokens).unwrap();
+        assert!(matches!(expr, Expr::Binary(ref operator, _, _) if operator.symbol == "/"));
+        assert_eq!(
+            TokenVec(&expr.to_rpn()).to_string(),
+            TokenVec(&tokens).to_string()
+        );
+
+        // 所有长度为4的序列往返转换后数值不变（超出定义域时都为 NaN）
+        let (operands, unary_operators, binary_operators) = prepare_opes();
+        for tokens in generate_valid_tokens_of_length(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            4,
+            GenConfig::default(),
+        ) {
+            let value = calculate(&tokens);
+            let round_trip = calculate(&Expr::from_rpn(&tokens).unwrap().to_rpn());
+            assert!(value == round_trip || (value.is_nan() && round_trip.is_nan()));
+        }
+    }
+
+    #[test]
+    fn test_simplify() {
+        let simplify = |rpn: &str| {
+            let expr = Expr::from_rpn(&parse_rpn(rpn).unwrap()).unwrap();
+            TokenVec(&expr.simplify().to_rpn()).to_string()
+        };
+        // 整数值的子表达式折叠为字面量
+        assert_eq!(simplify("e floor pi +"), "2 pi +");
+        assert_eq!(simplify("pi pi / e *"), "e");
+        assert_eq!(simplify("e e - pi +"), "pi");
+        assert_eq!(simplify("γ γ - 2 -"), "0 2 -");
+        assert_eq!(simplify("e floor pi ceil +"), "2 4 +"); // 整个表达式不折叠
+        // 多余的一元运算符
+        assert_eq!(simplify("pi asin"), "pi asin"); // 超出定义域，保持原样
+        assert_eq!(simplify("γ asin sin"), "γ");
+        assert_eq!(simplify("e abs abs"), "e abs");
+        assert_eq!(simplify("e sqrt abs"), "e sqrt");
+        // 恒等式
+        assert_eq!(simplify("pi e e / ^"), "pi");
+        assert_eq!(simplify("γ sin γ sin max"), "γ sin");
+        assert_eq!(simplify("pi sin e +"), "pi sin e +");
+
+        // 化简不改变数值
+        let (operands, unary_operators, binary_operators) = prepare_opes();
+        for tokens in generate_valid_tokens_of_length(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            4,
+            GenConfig::default(),
+        ) {
+            let expr = Expr::from_rpn(&tokens).unwrap();
+            let (simplified, value) = (expr.simplify().value(), calculate(&tokens));
+            assert!(
+                same_value(simplified, value, RELATIVE_EPSILON),
+                "{}",
+                TokenVec(&tokens)
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_invalid_rpn() {
+        let (_, unary_operators, binary_operators) = prepare_opes();
+        let pi = Token::Operand(Operand {
+            symbol: "pi".to_string(),
+            value: std::f64::consts::PI,
+        });
+        let sin = Token::UnaryOperator(unary_operators[0].clone());
+        let plus = Token::BinaryOperator(binary_operators[0].clone());
+        assert!(Expr::from_rpn(&[]).is_none());
+        assert!(Expr::from_rpn(std::slice::from_ref(&sin)).is_none());
+        assert!(Expr::from_rpn(&[pi.clone(), plus]).is_none());
+        assert!(Expr::from_rpn(&[pi.clone(), pi.clone()]).is_none());
+        assert!(Expr::from_rpn(&[pi, sin]).is_some());
+    }
+}
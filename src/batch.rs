@@ -0,0 +1,126 @@
+// src/batch.rs
+
+/// 批量搜索中的一行：名称、目标值及可选的容差
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchTarget {
+    pub name: String,           // 用于标记输出结果的名称
+    pub target: f64,            // 目标值
+    pub tolerance: Option<f64>, // 容差，`None` 表示使用命令行中的 --tolerance
+}
+
+/// 解析批量搜索的目标，每行一个 `name,target[,tolerance]`，空行和 `#` 开头的行会被忽略
+///
+/// 目标值必须是有限数，容差必须为正数，且至少要有一个目标。
+pub fn parse_batch(text: &str) -> Result<Vec<BatchTarget>, String> {
+    let targets = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let invalid = || format!("line {}: invalid number in {:?}", line_number, line);
+            let target = match fields[..] {
+                [name, target] | [name, target, _] if !name.is_empty() => BatchTarget {
+                    name: name.to_string(),
+                    target: target.parse().map_err(|_| invalid())?,
+                    tolerance: match fields.get(2) {
+                        Some(tolerance) => Some(tolerance.parse().map_err(|_| invalid())?),
+                        None => None,
+                    },
+                },
+                _ => {
+                    return Err(format!(
+                        "line {}: expected `name,target[,tolerance]`, got {:?}",
+                        line_number, line
+                    ));
+                }
+            };
+            if !target.target.is_finite() {
+                return Err(format!("line {}: target must be finite", line_number));
+            }
+            if target
+                .tolerance
+                .is_some_and(|tolerance| tolerance.is_nan() || tolerance <= 0.0)
+            {
+                return Err(format!("line {}: tolerance must be positive", line_number));
+            }
+            Ok(target)
+        })
+        .collect::<Result<Vec<BatchTarget>, String>>()?;
+    if targets.is_empty() {
+        return Err("no targets in batch file".to_string());
+    }
+    Ok(targets)
+}
+
+/// 在 `targets` 中找出与 `value` 最接近的目标值，`targets` 为空时返回 `None`
+pub fn nearest_target(targets: &[BatchTarget], value: f64) -> Option<f64> {
+    targets
+        .iter()
+        .map(|target| target.target)
+        .min_by(|a, b| (value - a).abs().total_cmp(&(value - b).abs()))
+}
+
+/// 从文件中读取批量搜索的目标
+pub fn load_batch(path: &str) -> Result<Vec<BatchTarget>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    parse_batch(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch() {
+        let targets =
+            parse_batch("# name,target,tolerance\nfine structure, 137.036, 1e-3\n\ng,9.80665\n")
+                .unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                BatchTarget {
+                    name: "fine structure".to_string(),
+                    target: 137.036,
+                    tolerance: Some(1e-3),
+                },
+                BatchTarget {
+                    name: "g".to_string(),
+                    target: 9.80665,
+                    tolerance: None,
+                },
+            ]
+        );
+        assert!(parse_batch("g").is_err());
+        assert!(parse_batch("g,x").is_err());
+        assert!(parse_batch(",1").is_err());
+        assert!(parse_batch("g,1,2,3").is_err());
+        // 非有限的目标值、非正的容差，错误信息中带有行号
+        assert_eq!(
+            parse_batch("g,1\nh,nan").unwrap_err(),
+            "line 2: target must be finite"
+        );
+        assert!(parse_batch("g,inf").is_err());
+        assert!(parse_batch("g,-inf").is_err());
+        assert_eq!(
+            parse_batch("\ng,1,-1e-3").unwrap_err(),
+            "line 2: tolerance must be positive"
+        );
+        assert!(parse_batch("g,1,0").is_err());
+        assert!(parse_batch("g,1,nan").is_err());
+        // 没有任何目标
+        assert!(parse_batch("").is_err());
+        assert!(parse_batch("# name,target\n\n").is_err());
+    }
+
+    #[test]
+    fn test_nearest_target() {
+        let targets = parse_batch("a,2.5\nb,3.25\nneg,-1").unwrap();
+        assert_eq!(nearest_target(&targets, 2.6), Some(2.5));
+        assert_eq!(nearest_target(&targets, 2.9), Some(3.25));
+        assert_eq!(nearest_target(&targets, 100.0), Some(3.25));
+        assert_eq!(nearest_target(&targets, -0.5), Some(-1.0));
+        assert_eq!(nearest_target(&[], 1.0), None);
+    }
+}
@@ -1,19 +1,132 @@
 // src/cli.rs
 use clap::Parser;
 
+use crate::output::{LogFormat, OutputFormat};
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    #[arg(short = 't', long)]
-    pub target: f64,
+    #[arg(
+        short = 't',
+        long,
+        required_unless_present_any = ["fit", "batch", "repl", "trace"],
+        conflicts_with = "fit"
+    )]
+    pub target: Option<f64>,
+    /// 拟合模式：从文件读取采样点 `x y`，寻找含自变量 x 的表达式，
+    /// 此时 --tolerance 表示误差平方和的阈值
+    #[arg(long)]
+    pub fit: Option<String>,
+    /// 批量模式：从文件读取多个目标，每行一个 `name,target[,tolerance]`，
+    /// 所有目标共用同一次生成和计算，输出结果以名称标记
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["target", "fit"])]
+    pub batch: Option<String>,
+    /// 交互模式：逐行读取RPN或中缀表达式并输出其值，输入 vars 查看可用的操作数和运算符
+    #[arg(long)]
+    pub repl: bool,
+    /// 输出单个RPN或中缀表达式的逐步计算过程后退出
+    #[arg(long, value_name = "EXPRESSION")]
+    pub trace: Option<String>,
     #[arg(short = 'd', long, default_value_t = 6)]
     pub max_depth: usize,
+    /// 按token总长度精确生成表达式，设置后忽略 --max-depth
+    #[arg(long)]
+    pub length: Option<usize>,
+    /// 随机采样模式：不再穷举，而是随机构造 N 个深度不超过 --max-depth 的表达式
+    #[arg(long, value_name = "N", conflicts_with = "length")]
+    pub sample: Option<u64>,
+    /// 随机采样的种子，种子相同时采样到的表达式相同
+    #[arg(long, default_value_t = 0, requires = "sample")]
+    pub seed: u64,
+    /// 生成时跳过紧跟在其逆运算或自身之后的一元运算符，如 sin(asin(x))、abs(abs(x))
+    #[arg(long)]
+    pub prune_trivial_unary: bool,
+    /// 作为操作数的常数，逗号分隔：e, pi, euler(γ), phi(φ), sqrt2(√2), ln2, catalan(G)
+    #[arg(long, value_delimiter = ',', default_value = "e,pi,euler")]
+    pub constants: Vec<String>,
+    /// 每个操作数在一个表达式中最多出现的次数（按符号计），1 表示操作数互不相同
+    #[arg(long)]
+    pub max_operand_uses: Option<usize>,
     #[arg(short = 'e', long, default_value_t = 1.0)]
     pub tolerance: f64,
+    /// 精确模式：按ULP距离（相隔的可表示浮点数个数）而非 --tolerance 判断是否等于目标值，
+    /// 用于验证表达式在浮点精度内恰好等于某个常数，如 sqrt(pi)^2 = pi
+    #[arg(long, conflicts_with = "fit")]
+    pub exact: bool,
+    /// 精确模式下允许的最大ULP距离
+    #[arg(long, value_name = "N", default_value_t = 4, requires = "exact")]
+    pub max_ulps: u64,
+    /// 中间结果绝对值的上限，超过该值的表达式会在计算中途被丢弃
+    #[arg(long, default_value_t = 1e12)]
+    pub max_magnitude: f64,
     #[arg(short = 'o', long)]
     pub output: Option<String>,
+    /// 日志的格式，json 便于用日志分析工具处理 --output 文件
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+    /// 在每个匹配结果后输出误差 value - target 和相对误差 error / target
+    #[arg(long)]
+    pub show_error: bool,
+    /// 输出数值和误差时保留的有效数字位数
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(1..=17))]
+    pub precision: u8,
+    /// 输出化简后的等价表达式，如把 `e floor` 写成 `2`、去掉 `sin(asin(x))` 中多余的运算符
+    #[arg(long, conflicts_with = "fit")]
+    pub simplify: bool,
+    /// 匹配结果的输出格式
+    #[arg(short = 'f', long, value_enum, default_value_t = OutputFormat::Human)]
+    pub format: OutputFormat,
     #[arg(short = 'c', long, default_value_t = 2 ^ 16)]
     pub chunk_size: usize,
     #[arg(short = 'n', long)]
     pub num_threads: Option<usize>,
+    /// 运行时间上限（秒），超时后停止搜索并输出已找到的结果
+    #[arg(long, value_name = "SECONDS", value_parser = non_negative_finite)]
+    pub timeout: Option<f64>,
+    /// 只输出至少包含这么多个token的表达式，更短的表达式仍会作为子表达式参与生成，
+    /// 并计入匹配数量、误差直方图和最接近的表达式
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub min_tokens: usize,
+    /// 只计算包含该符号的表达式，可重复指定，要求全部出现；常数也可以写 --constants 中的名称
+    #[arg(long, value_name = "SYMBOL")]
+    pub must_use: Vec<String>,
+    /// 缓存所有匹配结果，按表达式复杂度（token数、不同运算符数、误差）排序后输出
+    #[arg(long)]
+    pub sort_simple: bool,
+    /// 缓存所有匹配结果，按RPN字符串排序后输出，使多次运行的输出顺序一致
+    #[arg(long)]
+    pub deterministic: bool,
+    /// 只输出数值上互不相同的匹配结果，相差不超过 --unique-epsilon 的视为重复
+    #[arg(long)]
+    pub unique_values: bool,
+    /// --unique-values 判定为重复的数值误差，必须为正的有限数
+    #[arg(long, default_value_t = 1e-10, value_parser = positive_finite)]
+    pub unique_epsilon: f64,
+    /// 记录所有已计算的RPN序列，同一个序列最多计算和输出一次（如 --sample 重复采样到的序列），
+    /// 需要保存所有序列，内存占用与搜索空间成正比
+    #[arg(long)]
+    pub dedup_exact: bool,
+    /// 用 f128 重新计算 f64 阶段的候选表达式，按高精度结果判断是否满足 --tolerance，
+    /// 用于区分恒等式与仅在 f64 精度下接近的表达式
+    #[cfg(feature = "precision")]
+    #[arg(long, conflicts_with_all = ["fit", "exact"])]
+    pub high_precision: bool,
+}
+
+/// 解析正的有限数
+fn positive_finite(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+        Ok(_) => Err("must be a finite number greater than 0".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// 解析不小于0的有限数
+fn non_negative_finite(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+        Ok(_) => Err("must be a finite number not less than 0".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
 }
@@ -0,0 +1,157 @@
+// src/compile.rs
+use crate::rpn::is_valid_rpn;
+use crate::types::Token;
+
+/// 编译后的一条指令，运算符的函数指针和定义域已经从token中取出
+#[derive(Clone, Copy)]
+enum Instruction {
+    Constant(f64),   // 压入常数
+    Variable(usize), // 压入第 i 个变量的值
+    Unary {
+        function: fn(f64) -> f64,
+        domain: Option<fn(f64) -> bool>,
+    },
+    Binary {
+        function: fn(f64, f64) -> f64,
+        domain: Option<fn(f64, f64) -> bool>,
+    },
+}
+
+/// 编译后的RPN表达式，适合在不同的变量取值下反复求值
+///
+/// 编译时把每个token转换为扁平的指令，符号在 `variables` 中的操作数变为变量槽位，
+/// 求值时依次从参数切片中取值；求值复用同一个栈缓冲区，不再分配内存，
+/// 也不再克隆或比较符号字符串。
+pub struct Plan {
+    instructions: Vec<Instruction>,
+    stack: Vec<f64>, // 可复用的栈缓冲区
+}
+
+impl Plan {
+    /// 编译token序列，`variables[i]` 对应求值时参数切片中的第 i 个值
+    ///
+    /// # Panics
+    ///
+    /// 如果输入的 `tokens` 不是一个有效的 RPN 序列，该函数会 panic。
+    pub fn new(tokens: &[Token], variables: &[&str]) -> Self {
+        assert!(
+            is_valid_rpn(tokens),
+            "Invalid RPN sequence passed to Plan::new"
+        );
+
+        let mut depth = 0;
+        let mut max_depth = 0;
+        let instructions = tokens
+            .iter()
+            .map(|token| {
+                let instruction = match token {
+                    Token::Operand(operand) => {
+                        depth += 1;
+                        match variables.iter().position(|&name| name == operand.symbol) {
+                            Some(index) => Instruction::Variable(index),
+                            None => Instruction::Constant(operand.value),
+                        }
+                    }
+                    Token::UnaryOperator(operator) => Instruction::Unary {
+                        function: operator.function,
+                        domain: operator.domain,
+                    },
+                    Token::BinaryOperator(operator) => {
+                        depth -= 1;
+                        Instruction::Binary {
+                            function: operator.function,
+                            domain: operator.domain,
+                        }
+                    }
+                };
+                max_depth = max_depth.max(depth);
+                instruction
+            })
+            .collect();
+        Self {
+            instructions,
+            stack: Vec::with_capacity(max_depth),
+        }
+    }
+
+    /// 在给定的变量取值下求值，结果与对代入后的序列调用 `calculate` 相同
+    ///
+    /// # Panics
+    ///
+    /// `values` 比编译时的 `variables` 短时 panic。
+    pub fn evaluate(&mut self, values: &[f64]) -> f64 {
+        self.evaluate_with_limit(values, f64::INFINITY)
+    }
+
+    /// 与 `calculate_with_limit` 相同，中间结果的绝对值超过 `max_magnitude` 时返回 `NaN`
+    pub fn evaluate_with_limit(&mut self, values: &[f64], max_magnitude: f64) -> f64 {
+        let stack = &mut self.stack;
+        stack.clear();
+        for instruction in &self.instructions {
+            let result = match *instruction {
+                Instruction::Constant(value) => {
+                    stack.push(value);
+                    continue;
+                }
+                Instruction::Variable(index) => {
+                    stack.push(values[index]);
+                    continue;
+                }
+                Instruction::Unary { function, domain } => {
+                    let value = stack.pop().unwrap(); // 编译时已验证序列有效
+                    if domain.is_some_and(|in_domain| !in_domain(value)) {
+                        return f64::NAN; // 超出定义域
+                    }
+                    function(value)
+                }
+                Instruction::Binary { function, domain } => {
+                    let right = stack.pop().unwrap();
+                    let left = stack.pop().unwrap();
+                    if domain.is_some_and(|in_domain| !in_domain(left, right)) {
+                        return f64::NAN; // 超出定义域
+                    }
+                    function(left, right)
+                }
+            };
+            if result.abs() > max_magnitude {
+                return f64::NAN; // 中间结果过大
+            }
+            stack.push(result);
+        }
+        stack[0]
+    }
+}
+
+/// 把token序列编译为闭包，参数为 `variables` 中各变量的取值
+///
+/// 见 `Plan`。
+pub fn compile(tokens: &[Token], variables: &[&str]) -> impl FnMut(&[f64]) -> f64 + use<> {
+    let mut plan = Plan::new(tokens, variables);
+    move |values| plan.evaluate(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fit::substitute;
+    use crate::parser::parse_rpn;
+    use crate::rpn::calculate;
+
+    #[test]
+    fn test_plan_matches_calculate() {
+        let mut tokens = parse_rpn("2 3 + 4 * sqrt e 2 ^ -").unwrap();
+        assert_eq!(Plan::new(&tokens, &[]).evaluate(&[]), calculate(&tokens));
+
+        // 把常数 e 当作变量
+        let mut plan = Plan::new(&tokens, &["e"]);
+        for x in [0.0, 1.5, -2.0, 10.0] {
+            substitute(&mut tokens, "e", x);
+            assert_eq!(plan.evaluate(&[x]), calculate(&tokens));
+        }
+
+        let mut f = compile(&parse_rpn("2 asin").unwrap(), &[]);
+        assert!(f(&[]).is_nan()); // 超出定义域
+        let mut plan = Plan::new(&parse_rpn("10 10 ^ 10 ^").unwrap(), &[]);
+        assert!(plan.evaluate_with_limit(&[], 1e12).is_nan()); // 中间结果过大
+    }
+}
@@ -0,0 +1,121 @@
+// src/dedup.rs
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::types::{Token, TokenVec};
+
+/// 线程安全的数值集合，用于过滤数值上重复的匹配结果
+///
+/// 与某个已记录的数值相差不超过 `epsilon` 的值视为重复，相差更大的值都会被记录。
+/// 数值按 `epsilon` 向下取整后分桶，相差不超过 `epsilon` 的两个值一定位于相同或相邻的桶中，
+/// 插入时只需逐一比较这三个桶中的数值。
+pub struct ValueSet {
+    epsilon: f64,                            // 判定为重复的误差范围
+    buckets: Mutex<HashMap<i128, Vec<f64>>>, // 按取整后的键分桶记录的数值
+}
+
+impl ValueSet {
+    pub fn new(epsilon: f64) -> Self {
+        Self {
+            epsilon,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 尝试记录一个数值，若之前没有相差不超过 `epsilon` 的数值则返回 `true`
+    pub fn insert(&self, value: f64) -> bool {
+        let key = (value / self.epsilon).floor() as i128;
+        let mut buckets = self.buckets.lock().unwrap();
+        // 数值与 epsilon 之比超出 i128 的范围时键会饱和，相邻的键同样要饱和以免溢出
+        let duplicate = (key.saturating_sub(1)..=key.saturating_add(1))
+            .filter_map(|k| buckets.get(&k))
+            .flatten()
+            .any(|recorded| (value - recorded).abs() <= self.epsilon);
+        if duplicate {
+            return false;
+        }
+        buckets.entry(key).or_default().push(value);
+        true
+    }
+}
+
+/// 线程安全的RPN序列集合，用于跳过重复生成的token序列
+///
+/// 以RPN字符串为键，每个不同的序列只会被 `insert` 接受一次。
+#[derive(Default)]
+pub struct SequenceSet {
+    sequences: Mutex<HashSet<String>>, // 已记录序列的RPN字符串
+}
+
+impl SequenceSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 尝试记录一个序列，若之前没有记录过则返回 `true`
+    pub fn insert(&self, tokens: &[Token]) -> bool {
+        let key = TokenVec(tokens).to_string(); // 在加锁之前生成键，缩短持有锁的时间
+        self.sequences.lock().unwrap().insert(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_set_radius() {
+        let epsilon = 1e-3;
+        let set = ValueSet::new(epsilon);
+        assert!(set.insert(1.0));
+        assert!(!set.insert(1.0));
+        // 相差 0.9·epsilon 的值视为重复，不论是否位于同一个桶中
+        assert!(!set.insert(1.0 + 0.9 * epsilon));
+        assert!(!set.insert(1.0 - 0.9 * epsilon));
+        // 相差 1.6·epsilon 的值不是重复，即使位于相邻的桶中
+        assert!(set.insert(1.0 + 1.6 * epsilon));
+        assert!(set.insert(1.0 - 1.6 * epsilon));
+        // 0 附近正负两侧的桶同样相邻
+        assert!(set.insert(0.4 * epsilon));
+        assert!(!set.insert(-0.4 * epsilon));
+        assert!(set.insert(-1.2 * epsilon));
+
+        // 数值远大于 epsilon 时键饱和为 i128::MAX 或 i128::MIN，仍按实际数值比较
+        let set = ValueSet::new(1e-300);
+        assert!(set.insert(1e300));
+        assert!(!set.insert(1e300));
+        assert!(set.insert(2e300));
+        assert!(set.insert(-1e300));
+        assert!(!set.insert(-1e300));
+    }
+
+    #[test]
+    fn test_value_set_concurrent_insert() {
+        let set = ValueSet::new(1e-6);
+        // 4 个线程插入同样的 1000 个互不相近的数值，每个数值恰好被接受一次
+        let accepted: usize = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| (0..1000).filter(|&i| set.insert(i as f64 * 1e-3)).count()))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .sum()
+        });
+        assert_eq!(accepted, 1000);
+    }
+
+    #[test]
+    fn test_sequence_set() {
+        use crate::parser::parse_rpn;
+
+        let set = SequenceSet::new();
+        assert!(set.insert(&parse_rpn("pi e +").unwrap()));
+        // 同一个序列再次出现
+        assert!(!set.insert(&parse_rpn("pi e +").unwrap()));
+        // 数值相同但序列不同的表达式都会被接受
+        assert!(set.insert(&parse_rpn("e pi +").unwrap()));
+        assert!(set.insert(&parse_rpn("pi e + abs").unwrap()));
+        assert!(!set.insert(&parse_rpn("e pi +").unwrap()));
+    }
+}
@@ -0,0 +1,161 @@
+// src/fit.rs
+use crate::compile::Plan;
+use crate::types::{Operand, Token};
+
+/// 拟合模式中自变量的符号
+pub const VARIABLE_SYMBOL: &str = "x";
+
+/// 一个采样点 `(x, y)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// 自变量操作数，其数值在每次计算前由 `substitute` 填入
+pub fn variable() -> Operand {
+    Operand {
+        symbol: VARIABLE_SYMBOL.to_string(),
+        value: f64::NAN,
+    }
+}
+
+/// 解析采样点，每行一个 `x y`（空格或逗号分隔），空行和 `#` 开头的行会被忽略
+///
+/// 坐标必须是有限数，且至少要有一个采样点。
+pub fn parse_samples(text: &str) -> Result<Vec<Sample>, String> {
+    let samples = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| {
+            let fields: Vec<&str> = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|field| !field.is_empty())
+                .collect();
+            let sample = match fields[..] {
+                [x, y] => match (x.parse(), y.parse()) {
+                    (Ok(x), Ok(y)) => Sample { x, y },
+                    _ => {
+                        return Err(format!(
+                            "line {}: invalid number in {:?}",
+                            line_number, line
+                        ));
+                    }
+                },
+                _ => {
+                    return Err(format!(
+                        "line {}: expected `x y`, got {:?}",
+                        line_number, line
+                    ));
+                }
+            };
+            if !sample.x.is_finite() || !sample.y.is_finite() {
+                return Err(format!("line {}: sample must be finite", line_number));
+            }
+            Ok(sample)
+        })
+        .collect::<Result<Vec<Sample>, String>>()?;
+    if samples.is_empty() {
+        return Err("no samples in fit file".to_string());
+    }
+    Ok(samples)
+}
+
+/// 从文件中读取采样点
+pub fn load_samples(path: &str) -> Result<Vec<Sample>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    parse_samples(&text)
+}
+
+/// 把序列中所有符号为 `symbol` 的操作数替换为 `value`
+pub fn substitute(tokens: &mut [Token], symbol: &str, value: f64) {
+    for token in tokens {
+        if let Token::Operand(operand) = token
+            && operand.symbol == symbol
+        {
+            operand.value = value;
+        }
+    }
+}
+
+/// 计算表达式在所有采样点上的误差平方和
+///
+/// 任意一个采样点的计算结果无效时返回 `NaN`。
+pub fn sum_squared_error(tokens: &[Token], samples: &[Sample], max_magnitude: f64) -> f64 {
+    // 编译一次，在每个采样点上复用
+    let mut plan = Plan::new(tokens, &[VARIABLE_SYMBOL]);
+    samples
+        .iter()
+        .map(|sample| {
+            let value = plan.evaluate_with_limit(&[sample.x], max_magnitude);
+            (value - sample.y).powi(2)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opes_data::prepare_opes;
+    use crate::parser::parse_rpn_with;
+    use crate::rpn::calculate;
+
+    /// 解析含自变量 x 的RPN表达式
+    fn parse(rpn: &str) -> Vec<Token> {
+        let (_, unary_operators, binary_operators) = prepare_opes();
+        parse_rpn_with(rpn, &[variable()], &unary_operators, &binary_operators).unwrap()
+    }
+
+    #[test]
+    fn test_parse_samples() {
+        let samples = parse_samples("# x y\n0 1\n\n1.5, -2\n").unwrap();
+        assert_eq!(
+            samples,
+            vec![Sample { x: 0.0, y: 1.0 }, Sample { x: 1.5, y: -2.0 }]
+        );
+        assert!(parse_samples("1 2 3").is_err());
+        assert!(parse_samples("a b").is_err());
+        // 非有限的坐标，错误信息中带有行号
+        assert_eq!(
+            parse_samples("0 1\n1 nan").unwrap_err(),
+            "line 2: sample must be finite"
+        );
+        assert!(parse_samples("inf 1").is_err());
+        assert!(parse_samples("1, -inf").is_err());
+        // 没有任何采样点
+        assert!(parse_samples("").is_err());
+        assert!(parse_samples("# x y\n\n").is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut tokens = parse("x x * 1 +");
+        assert!(calculate(&tokens).is_nan()); // 未填入自变量
+        substitute(&mut tokens, VARIABLE_SYMBOL, 3.0);
+        assert_eq!(calculate(&tokens), 10.0);
+        substitute(&mut tokens, VARIABLE_SYMBOL, -2.0);
+        assert_eq!(calculate(&tokens), 5.0);
+        // 其他操作数不受影响
+        substitute(&mut tokens, "y", 0.0);
+        assert_eq!(calculate(&tokens), 5.0);
+    }
+
+    #[test]
+    fn test_sum_squared_error() {
+        let samples = [
+            Sample { x: 0.0, y: 1.0 },
+            Sample { x: 1.0, y: 3.0 },
+            Sample { x: 2.0, y: 5.0 },
+        ];
+        assert_eq!(sum_squared_error(&parse("x 2 * 1 +"), &samples, 1e12), 0.0);
+        // 残差为 1、2、3
+        assert_eq!(sum_squared_error(&parse("x"), &samples, 1e12), 14.0);
+        // 不含自变量的表达式在每个采样点上的值相同
+        assert_eq!(sum_squared_error(&parse("3"), &samples, 1e12), 8.0);
+        // x = 0 时 ln(-1) 超出定义域，中间结果超过上限时同样无效
+        assert!(sum_squared_error(&parse("x 1 - ln"), &samples, 1e12).is_nan());
+        assert!(sum_squared_error(&parse("x 1e9 *"), &samples, 1e9).is_nan());
+    }
+}
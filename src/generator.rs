@@ -1,6 +1,47 @@
 // src/generator.rs
+use crate::opes_data::TRIVIAL_UNARY_PAIRS;
 use crate::types::{BinaryOperator, Operand, Token, UnaryOperator};
+use rand::{Rng, RngExt};
 use std::iter;
+use std::ops::ControlFlow;
+
+/// 生成过程中的剪枝选项
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GenConfig {
+    pub prune_trivial_unary: bool, // 禁止一元运算符紧跟在其逆运算或自身之后
+    pub max_operand_uses: Option<usize>, // 每个操作数（按符号计）在一个表达式中最多出现的次数
+}
+
+/// 判断在当前序列后追加一元运算符 `next` 是否只会得到无意义的组合
+fn is_trivial_unary(config: GenConfig, sequence: &[Token], next: &UnaryOperator) -> bool {
+    if !config.prune_trivial_unary {
+        return false;
+    }
+    match sequence.last() {
+        Some(Token::UnaryOperator(previous)) => TRIVIAL_UNARY_PAIRS
+            .iter()
+            .any(|(inner, outer)| *inner == previous.symbol && *outer == next.symbol),
+        _ => false,
+    }
+}
+
+/// 判断计数位置 `slot` 上的操作数是否已达到使用次数上限
+fn is_operand_exhausted(config: GenConfig, operand_uses: &[usize], slot: usize) -> bool {
+    config
+        .max_operand_uses
+        .is_some_and(|max_uses| operand_uses[slot] >= max_uses)
+}
+
+/// 第 `index` 个操作数的使用次数记在哪个位置上
+///
+/// 使用次数按符号计算：符号相同的操作数（如重复提供的同一个常数）共用第一个的位置。
+fn use_slot(operands: &[Operand], index: usize) -> usize {
+    let symbol = &operands[index].symbol;
+    operands
+        .iter()
+        .position(|operand| operand.symbol == *symbol)
+        .unwrap_or(index)
+}
 
 /// 生成有效的token序列
 pub fn generate_valid_tokens<'a>(
@@ -8,23 +49,27 @@ pub fn generate_valid_tokens<'a>(
     unary_operators: &'a [UnaryOperator],   // 可用的一元运算符列表
     binary_operators: &'a [BinaryOperator], // 可用的二元运算符列表
     max_depth: usize,                       // 最大深度限制
+    config: GenConfig,                      // 生成选项
 ) -> impl Iterator<Item = Vec<Token>> + 'a {
     // 从1到max_depth遍历所有可能的深度
     (1..=max_depth).flat_map(move |depth| {
-        generate_valid_tokens_with_depth(operands, unary_operators, binary_operators, depth)
+        generate_valid_tokens_with_depth(operands, unary_operators, binary_operators, depth, config)
     })
 }
 
 /// 辅助函数：生成指定深度的有效token序列
 /// 该函数使用递归方式生成所有可能的有效RPN表达式
+#[allow(clippy::too_many_arguments)]
 fn aux_generate<'a>(
     operands: &'a [Operand],                // 可用的操作数列表
     unary_operators: &'a [UnaryOperator],   // 可用的一元运算符列表
     binary_operators: &'a [BinaryOperator], // 可用的二元运算符列表
+    config: GenConfig,                      // 生成选项
     operands_needed: usize,                 // 还需要多少个操作数
     unary_ops_needed: usize,                // 还需要多少个一元运算符
     binary_ops_needed: usize,               // 还需要多少个二元运算符
     current_sequence: Vec<Token>,           // 当前已生成的序列
+    operand_uses: Vec<usize>,               // 每个操作数已使用的次数
     stack_size: usize,                      // 当前栈的大小
 ) -> Box<dyn Iterator<Item = Vec<Token>> + 'a> {
     // 基本情况：所有需要的token都已生成
@@ -43,17 +88,26 @@ fn aux_generate<'a>(
     let operand_iter: Box<dyn Iterator<Item = Vec<Token>> + 'a> =
         if operands_needed > 0 && !operands.is_empty() {
             let current_sequence_clone = current_sequence.clone();
-            let iter = operands.iter().flat_map(move |op| {
+            let operand_uses_clone = operand_uses.clone();
+            let iter = operands.iter().enumerate().flat_map(move |(index, op)| {
+                let slot = use_slot(operands, index);
+                if is_operand_exhausted(config, &operand_uses_clone, slot) {
+                    return Box::new(iter::empty()) as Box<dyn Iterator<Item = Vec<Token>> + 'a>;
+                }
                 let mut next_sequence = current_sequence_clone.clone();
                 next_sequence.push(Token::Operand(op.clone()));
+                let mut next_uses = operand_uses_clone.clone();
+                next_uses[slot] += 1;
                 aux_generate(
                     operands,
                     unary_operators,
                     binary_operators,
+                    config,
                     operands_needed - 1,
                     unary_ops_needed,
                     binary_ops_needed,
                     next_sequence,
+                    next_uses,
                     stack_size + 1,
                 )
             });
@@ -66,17 +120,23 @@ fn aux_generate<'a>(
     let unary_iter: Box<dyn Iterator<Item = Vec<Token>> + 'a> =
         if unary_ops_needed > 0 && stack_size >= 1 && !unary_operators.is_empty() {
             let current_sequence_clone = current_sequence.clone();
+            let operand_uses_clone = operand_uses.clone();
             let iter = unary_operators.iter().flat_map(move |uop| {
+                if is_trivial_unary(config, &current_sequence_clone, uop) {
+                    return Box::new(iter::empty()) as Box<dyn Iterator<Item = Vec<Token>> + 'a>;
+                }
                 let mut next_sequence = current_sequence_clone.clone();
                 next_sequence.push(Token::UnaryOperator(uop.clone()));
                 aux_generate(
                     operands,
                     unary_operators,
                     binary_operators,
+                    config,
                     operands_needed,
                     unary_ops_needed - 1,
                     binary_ops_needed,
                     next_sequence,
+                    operand_uses_clone.clone(),
                     stack_size, // 一元操作符不改变栈大小
                 )
             });
@@ -89,6 +149,7 @@ fn aux_generate<'a>(
     let binary_iter: Box<dyn Iterator<Item = Vec<Token>> + 'a> =
         if binary_ops_needed > 0 && stack_size >= 2 && !binary_operators.is_empty() {
             let current_sequence_clone = current_sequence.clone();
+            let operand_uses_clone = operand_uses.clone();
             let iter = binary_operators.iter().flat_map(move |bop| {
                 let mut next_sequence = current_sequence_clone.clone();
                 next_sequence.push(Token::BinaryOperator(bop.clone()));
@@ -96,10 +157,12 @@ fn aux_generate<'a>(
                     operands,
                     unary_operators,
                     binary_operators,
+                    config,
                     operands_needed,
                     unary_ops_needed,
                     binary_ops_needed - 1,
                     next_sequence,
+                    operand_uses_clone.clone(),
                     stack_size - 1, // 二元操作符使栈大小减1
                 )
             });
@@ -112,6 +175,126 @@ fn aux_generate<'a>(
     Box::new(operand_iter.chain(unary_iter).chain(binary_iter))
 }
 
+/// 生成恰好由 `length` 个token组成的所有有效RPN序列
+///
+/// 与按深度生成不同，这里不区分操作数和运算符的数量，
+/// 只要求序列总长度为 `length` 且最终栈中恰好剩下一个结果
+pub fn generate_valid_tokens_of_length<'a>(
+    operands: &'a [Operand],                // 可用的操作数列表
+    unary_operators: &'a [UnaryOperator],   // 可用的一元运算符列表
+    binary_operators: &'a [BinaryOperator], // 可用的二元运算符列表
+    length: usize,                          // 序列的总长度
+    config: GenConfig,                      // 生成选项
+) -> Box<dyn Iterator<Item = Vec<Token>> + 'a> {
+    aux_generate_of_length(
+        operands,
+        unary_operators,
+        binary_operators,
+        config,
+        length,
+        Vec::new(),
+        vec![0; operands.len()],
+        0,
+    )
+}
+
+/// 辅助函数：递归生成剩余 `tokens_left` 个token的有效序列
+#[allow(clippy::too_many_arguments)]
+fn aux_generate_of_length<'a>(
+    operands: &'a [Operand],                // 可用的操作数列表
+    unary_operators: &'a [UnaryOperator],   // 可用的一元运算符列表
+    binary_operators: &'a [BinaryOperator], // 可用的二元运算符列表
+    config: GenConfig,                      // 生成选项
+    tokens_left: usize,                     // 还需要生成多少个token
+    current_sequence: Vec<Token>,           // 当前已生成的序列
+    operand_uses: Vec<usize>,               // 每个操作数已使用的次数
+    stack_size: usize,                      // 当前栈的大小
+) -> Box<dyn Iterator<Item = Vec<Token>> + 'a> {
+    if tokens_left == 0 {
+        if stack_size == 1 {
+            return Box::new(iter::once(current_sequence));
+        } else {
+            return Box::new(iter::empty());
+        }
+    }
+    // 每个二元运算符只能让栈大小减1，剩余的token不足以把栈归约到1时直接剪枝
+    if stack_size > tokens_left + 1 {
+        return Box::new(iter::empty());
+    }
+
+    // 生成操作数的迭代器
+    let current_sequence_clone = current_sequence.clone();
+    let operand_uses_clone = operand_uses.clone();
+    let operand_iter = operands.iter().enumerate().flat_map(move |(index, op)| {
+        let slot = use_slot(operands, index);
+        if is_operand_exhausted(config, &operand_uses_clone, slot) {
+            return Box::new(iter::empty()) as Box<dyn Iterator<Item = Vec<Token>> + 'a>;
+        }
+        let mut next_sequence = current_sequence_clone.clone();
+        next_sequence.push(Token::Operand(op.clone()));
+        let mut next_uses = operand_uses_clone.clone();
+        next_uses[slot] += 1;
+        aux_generate_of_length(
+            operands,
+            unary_operators,
+            binary_operators,
+            config,
+            tokens_left - 1,
+            next_sequence,
+            next_uses,
+            stack_size + 1,
+        )
+    });
+
+    // 生成一元运算符的迭代器
+    let unary_iter: Box<dyn Iterator<Item = Vec<Token>> + 'a> = if stack_size >= 1 {
+        let current_sequence_clone = current_sequence.clone();
+        let operand_uses_clone = operand_uses.clone();
+        Box::new(unary_operators.iter().flat_map(move |uop| {
+            if is_trivial_unary(config, &current_sequence_clone, uop) {
+                return Box::new(iter::empty()) as Box<dyn Iterator<Item = Vec<Token>> + 'a>;
+            }
+            let mut next_sequence = current_sequence_clone.clone();
+            next_sequence.push(Token::UnaryOperator(uop.clone()));
+            aux_generate_of_length(
+                operands,
+                unary_operators,
+                binary_operators,
+                config,
+                tokens_left - 1,
+                next_sequence,
+                operand_uses_clone.clone(),
+                stack_size, // 一元操作符不改变栈大小
+            )
+        }))
+    } else {
+        Box::new(iter::empty())
+    };
+
+    // 生成二元运算符的迭代器
+    let binary_iter: Box<dyn Iterator<Item = Vec<Token>> + 'a> = if stack_size >= 2 {
+        let operand_uses_clone = operand_uses;
+        Box::new(binary_operators.iter().flat_map(move |bop| {
+            let mut next_sequence = current_sequence.clone();
+            next_sequence.push(Token::BinaryOperator(bop.clone()));
+            aux_generate_of_length(
+                operands,
+                unary_operators,
+                binary_operators,
+                config,
+                tokens_left - 1,
+                next_sequence,
+                operand_uses_clone.clone(),
+                stack_size - 1, // 二元操作符使栈大小减1
+            )
+        }))
+    } else {
+        Box::new(iter::empty())
+    };
+
+    // 合并所有生成的迭代器
+    Box::new(operand_iter.chain(unary_iter).chain(binary_iter))
+}
 
 /// 生成指定深度的有效token序列
 pub fn generate_valid_tokens_with_depth<'a>(
@@ -119,6 +302,7 @@ pub fn generate_valid_tokens_with_depth<'a>(
     unary_operators: &'a [UnaryOperator],   // 可用的一元运算符列表
     binary_operators: &'a [BinaryOperator], // 可用的二元运算符列表
     depth: usize,                           // 目标深度
+    config: GenConfig,                      // 生成选项
 ) -> Box<dyn Iterator<Item = Vec<Token>> + 'a> { // Changed return type to Box<dyn Iterator>
     // 计算需要多少个操作数和运算符才能达到指定的深度
     // RPN 中，n 个操作数需要 n-1 个二元运算符
@@ -145,11 +329,13 @@ pub fn generate_valid_tokens_with_depth<'a>(
                 operands,
                 unary_operators,
                 binary_operators,
+                config,
                 num_operands,
                 num_unary_ops,
                 num_binary_ops,
                 Vec::new(), // Start with an empty sequence
-                0,          // Start with stack size 0
+                vec![0; operands.len()],
+                0, // Start with stack size 0
             )
         })
         // Filter results that might not be valid RPN (although aux_generate aims for valid structure)
@@ -158,3 +344,713 @@ pub fn generate_valid_tokens_with_depth<'a>(
         // .filter(|tokens| crate::rpn::is_valid_rpn(tokens)) // Removed potential redundant check
     )
 }
+
+/// 随机构造一个深度不超过 `max_depth` 的有效token序列
+///
+/// 与 `generate_valid_tokens` 的划分方式相同，先随机选取深度和一元运算符的个数，
+/// 再逐个随机选取当前栈大小下合法的操作数、一元或二元运算符，生成的序列总是有效的。
+/// 剪枝选项使某一步没有合法的选择时（如操作数已全部用尽）返回 `None`。
+pub fn sample_valid_tokens(
+    operands: &[Operand],                // 可用的操作数列表
+    unary_operators: &[UnaryOperator],   // 可用的一元运算符列表
+    binary_operators: &[BinaryOperator], // 可用的二元运算符列表
+    max_depth: usize,                    // 最大深度限制
+    config: GenConfig,                   // 生成选项
+    rng: &mut impl Rng,                  // 随机数生成器，种子相同时结果相同
+) -> Option<Vec<Token>> {
+    if max_depth == 0 || operands.is_empty() {
+        return None;
+    }
+    // 没有二元运算符时只能构造深度为1的序列
+    let max_depth = if binary_operators.is_empty() {
+        1
+    } else {
+        max_depth
+    };
+    let depth = rng.random_range(1..=max_depth);
+    let mut operands_needed = depth;
+    let mut unary_ops_needed = if unary_operators.is_empty() {
+        0
+    } else {
+        rng.random_range(0..=depth)
+    };
+    let mut binary_ops_needed = depth - 1;
+    let mut sequence = Vec::with_capacity(operands_needed + unary_ops_needed + binary_ops_needed);
+    let mut operand_uses = vec![0; operands.len()];
+    let mut stack_size = 0;
+
+    while operands_needed + unary_ops_needed + binary_ops_needed > 0 {
+        // 当前栈大小下合法的token种类：0 操作数、1 一元运算符、2 二元运算符
+        let mut kinds = Vec::with_capacity(3);
+        if operands_needed > 0 {
+            kinds.push(0);
+        }
+        if unary_ops_needed > 0 && stack_size >= 1 {
+            kinds.push(1);
+        }
+        if binary_ops_needed > 0 && stack_size >= 2 {
+            kinds.push(2);
+        }
+        let token = match kinds[rng.random_range(0..kinds.len())] {
+            0 => {
+                let available: Vec<usize> = (0..operands.len())
+                    .filter(|&index| {
+                        !is_operand_exhausted(config, &operand_uses, use_slot(operands, index))
+                    })
+                    .collect();
+                let index = *available.get(rng.random_range(0..available.len().max(1)))?;
+                operand_uses[use_slot(operands, index)] += 1;
+                operands_needed -= 1;
+                stack_size += 1;
+                Token::Operand(operands[index].clone())
+            }
+            1 => {
+                let available: Vec<&UnaryOperator> = unary_operators
+                    .iter()
+                    .filter(|operator| !is_trivial_unary(config, &sequence, operator))
+                    .collect();
+                let operator = *available.get(rng.random_range(0..available.len().max(1)))?;
+                unary_ops_needed -= 1;
+                Token::UnaryOperator(operator.clone())
+            }
+            _ => {
+                binary_ops_needed -= 1;
+                stack_size -= 1;
+                Token::BinaryOperator(
+                    binary_operators[rng.random_range(0..binary_operators.len())].clone(),
+                )
+            }
+        };
+        sequence.push(token);
+    }
+    Some(sequence)
+}
+
+/// 搜索空间中可以独立生成的一部分，用于并行生成token序列
+///
+/// RPN序列的第一个token必然是操作数，因此按第一个操作数划分，
+/// 所有划分生成的序列合起来与整体生成的结果完全相同。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Partition {
+    /// 深度为 `depth`、恰好包含 `unary_ops` 个一元运算符、以第 `first_operand` 个操作数开头的序列
+    Depth {
+        depth: usize,
+        unary_ops: usize,
+        first_operand: usize,
+    },
+    /// 总长度为 `length`、以第 `first_operand` 个操作数开头的序列
+    Length { length: usize, first_operand: usize },
+}
+
+/// 把 `generate_valid_tokens` 的搜索空间划分为多个部分
+pub fn partitions_by_depth(num_operands: usize, max_depth: usize) -> Vec<Partition> {
+    let mut partitions = Vec::new();
+    for depth in 1..=max_depth {
+        for unary_ops in 0..=depth {
+            for first_operand in 0..num_operands {
+                partitions.push(Partition::Depth {
+                    depth,
+                    unary_ops,
+                    first_operand,
+                });
+            }
+        }
+    }
+    partitions
+}
+
+/// 把 `generate_valid_tokens_of_length` 的搜索空间划分为多个部分
+pub fn partitions_by_length(num_operands: usize, length: usize) -> Vec<Partition> {
+    if length == 0 {
+        return Vec::new(); // 长度为0时没有有效序列
+    }
+    (0..num_operands)
+        .map(|first_operand| Partition::Length {
+            length,
+            first_operand,
+        })
+        .collect()
+}
+
+/// 以回调方式遍历某个划分中的所有有效token序列
+///
+/// 深度优先地在同一个缓冲区上追加和回退token，不创建嵌套的迭代器，
+/// 内存占用只与序列长度有关；`visit` 收到的切片在回调返回后即被覆盖，需要保留时自行复制。
+/// `visit` 返回 `ControlFlow::Break` 时立即停止遍历并返回 `Break`。
+pub fn visit_partition<F>(
+    operands: &[Operand],                // 可用的操作数列表
+    unary_operators: &[UnaryOperator],   // 可用的一元运算符列表
+    binary_operators: &[BinaryOperator], // 可用的二元运算符列表
+    partition: Partition,                // 要遍历的划分
+    config: GenConfig,                   // 生成选项
+    mut visit: F,                        // 对每个有效序列调用的回调
+) -> ControlFlow<()>
+where
+    F: FnMut(&[Token]) -> ControlFlow<()>,
+{
+    let first_operand = match partition {
+        Partition::Depth { first_operand, .. } | Partition::Length { first_operand, .. } => {
+            first_operand
+        }
+    };
+    let mut operand_uses = vec![0; operands.len()];
+    let first_slot = use_slot(operands, first_operand);
+    if is_operand_exhausted(config, &operand_uses, first_slot) {
+        return ControlFlow::Continue(()); // 第一个操作数就已超出使用次数上限
+    }
+    operand_uses[first_slot] = 1;
+
+    let mut walk = Walk {
+        operands,
+        unary_operators,
+        binary_operators,
+        config,
+        sequence: vec![Token::Operand(operands[first_operand].clone())],
+        operand_uses,
+        visit: &mut visit,
+    };
+    match partition {
+        Partition::Depth {
+            depth, unary_ops, ..
+        } => walk.by_counts(depth - 1, unary_ops, depth - 1, 1),
+        Partition::Length { length, .. } => walk.by_length(length - 1, 1),
+    }
+}
+
+/// `visit_partition` 的遍历状态
+struct Walk<'a, F> {
+    operands: &'a [Operand],
+    unary_operators: &'a [UnaryOperator],
+    binary_operators: &'a [BinaryOperator],
+    config: GenConfig,
+    sequence: Vec<Token>,     // 当前已生成的序列，遍历时原地追加和回退
+    operand_uses: Vec<usize>, // 每个操作数已使用的次数，按 `use_slot` 计数
+    visit: &'a mut F,
+}
+
+impl<F: FnMut(&[Token]) -> ControlFlow<()>> Walk<'_, F> {
+    /// 追加一个token后执行 `next`，返回前回退
+    fn with_token(
+        &mut self,
+        token: Token,
+        next: impl FnOnce(&mut Self) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        self.sequence.push(token);
+        let flow = next(self);
+        self.sequence.pop();
+        flow
+    }
+
+    /// 依次尝试每个可用的操作数
+    fn each_operand(
+        &mut self,
+        mut next: impl FnMut(&mut Self) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        for index in 0..self.operands.len() {
+            let slot = use_slot(self.operands, index);
+            if is_operand_exhausted(self.config, &self.operand_uses, slot) {
+                continue;
+            }
+            self.operand_uses[slot] += 1;
+            let flow = self.with_token(Token::Operand(self.operands[index].clone()), &mut next);
+            self.operand_uses[slot] -= 1;
+            flow?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// 依次尝试每个不会构成无意义组合的一元运算符
+    fn each_unary(
+        &mut self,
+        mut next: impl FnMut(&mut Self) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        for operator in self.unary_operators {
+            if is_trivial_unary(self.config, &self.sequence, operator) {
+                continue;
+            }
+            self.with_token(Token::UnaryOperator(operator.clone()), &mut next)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// 依次尝试每个二元运算符
+    fn each_binary(
+        &mut self,
+        mut next: impl FnMut(&mut Self) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        for operator in self.binary_operators {
+            self.with_token(Token::BinaryOperator(operator.clone()), &mut next)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// 与 `aux_generate` 相同：恰好再追加给定数量的操作数、一元和二元运算符
+    fn by_counts(
+        &mut self,
+        operands_needed: usize,
+        unary_ops_needed: usize,
+        binary_ops_needed: usize,
+        stack_size: usize,
+    ) -> ControlFlow<()> {
+        if operands_needed == 0 && unary_ops_needed == 0 && binary_ops_needed == 0 {
+            if stack_size == 1 {
+                return (self.visit)(&self.sequence);
+            }
+            return ControlFlow::Continue(());
+        }
+        if operands_needed > 0 {
+            self.each_operand(|walk| {
+                walk.by_counts(
+                    operands_needed - 1,
+                    unary_ops_needed,
+                    binary_ops_needed,
+                    stack_size + 1,
+                )
+            })?;
+        }
+        if unary_ops_needed > 0 && stack_size >= 1 {
+            self.each_unary(|walk| {
+                walk.by_counts(
+                    operands_needed,
+                    unary_ops_needed - 1,
+                    binary_ops_needed,
+                    stack_size,
+                )
+            })?;
+        }
+        if binary_ops_needed > 0 && stack_size >= 2 {
+            self.each_binary(|walk| {
+                walk.by_counts(
+                    operands_needed,
+                    unary_ops_needed,
+                    binary_ops_needed - 1,
+                    stack_size - 1,
+                )
+            })?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// 与 `aux_generate_of_length` 相同：恰好再追加 `tokens_left` 个token
+    fn by_length(&mut self, tokens_left: usize, stack_size: usize) -> ControlFlow<()> {
+        if tokens_left == 0 {
+            if stack_size == 1 {
+                return (self.visit)(&self.sequence);
+            }
+            return ControlFlow::Continue(());
+        }
+        // 剩余的token不足以把栈归约到1时直接剪枝
+        if stack_size > tokens_left + 1 {
+            return ControlFlow::Continue(());
+        }
+        self.each_operand(|walk| walk.by_length(tokens_left - 1, stack_size + 1))?;
+        if stack_size >= 1 {
+            self.each_unary(|walk| walk.by_length(tokens_left - 1, stack_size))?;
+        }
+        if stack_size >= 2 {
+            self.each_binary(|walk| walk.by_length(tokens_left - 1, stack_size - 1))?;
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TokenVec;
+
+    #[test]
+    fn test_generate_valid_tokens_of_length() {
+        let operands = vec![
+            Operand {
+                symbol: "a".to_string(),
+                value: 1.0,
+            },
+            Operand {
+                symbol: "b".to_string(),
+                value: 2.0,
+            },
+        ];
+        let unary_operators = vec![UnaryOperator::new("u".to_string(), |a| -a)];
+        let binary_operators = vec![BinaryOperator::new("+".to_string(), |a, b| a + b)];
+
+        let mut sequences: Vec<String> = generate_valid_tokens_of_length(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            3,
+            GenConfig::default(),
+        )
+        .map(|tokens| TokenVec(&tokens).to_string())
+        .collect();
+        sequences.sort();
+        // 长度为3时只有 操作数-操作数-二元 和 操作数-一元-一元 两种结构
+        assert_eq!(
+            sequences,
+            vec!["a a +", "a b +", "a u u", "b a +", "b b +", "b u u"]
+        );
+
+        // 每个操作数最多使用一次时，a a + 和 b b + 不再出现
+        let mut sequences: Vec<String> = generate_valid_tokens_of_length(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            3,
+            GenConfig {
+                max_operand_uses: Some(1),
+                ..GenConfig::default()
+            },
+        )
+        .map(|tokens| TokenVec(&tokens).to_string())
+        .collect();
+        sequences.sort();
+        assert_eq!(sequences, vec!["a b +", "a u u", "b a +", "b u u"]);
+
+        // 长度为0时没有有效序列
+        assert_eq!(
+            generate_valid_tokens_of_length(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                0,
+                GenConfig::default(),
+            )
+            .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_prune_trivial_unary() {
+        let (operands, unary_operators, binary_operators) = crate::opes_data::prepare_opes();
+        let pruned = GenConfig {
+            prune_trivial_unary: true,
+            ..GenConfig::default()
+        };
+        let generate = |operands: &[Operand],
+                        unary_operators: &[UnaryOperator],
+                        binary_operators: &[BinaryOperator],
+                        config: GenConfig| {
+            generate_valid_tokens_of_length(operands, unary_operators, binary_operators, 3, config)
+                .map(|tokens| TokenVec(&tokens).to_string())
+                .collect::<Vec<String>>()
+        };
+
+        // pi 之后接两个一元运算符共9种，再加上 pi pi +；剪去 asin -> sin 和 abs -> abs
+        let pi = &operands[1..2];
+        let unary: Vec<UnaryOperator> = unary_operators
+            .iter()
+            .filter(|operator| ["sin", "asin", "abs"].contains(&operator.symbol.as_str()))
+            .cloned()
+            .collect();
+        let plus = &binary_operators[..1];
+        let all = generate(pi, &unary, plus, GenConfig::default());
+        let kept = generate(pi, &unary, plus, pruned);
+        assert_eq!(all.len(), 10);
+        assert_eq!(kept.len(), 8);
+        assert!(!kept.contains(&"pi asin sin".to_string()));
+        assert!(!kept.contains(&"pi abs abs".to_string()));
+        assert!(kept.contains(&"pi sin asin".to_string())); // 反方向不是恒等变换
+
+        // 完整的运算符表：剪枝后没有相邻的无意义组合，其余序列都保留
+        let all = generate(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            GenConfig::default(),
+        );
+        let kept = generate(&operands, &unary_operators, &binary_operators, pruned);
+        let is_trivial = |rpn: &String| {
+            let words: Vec<&str> = rpn.split(' ').collect();
+            words
+                .windows(2)
+                .any(|pair| TRIVIAL_UNARY_PAIRS.contains(&(pair[0], pair[1])))
+        };
+        assert!(kept.len() < all.len());
+        assert!(!kept.iter().any(is_trivial));
+        assert_eq!(
+            all.iter()
+                .filter(|rpn| !is_trivial(rpn))
+                .collect::<Vec<_>>(),
+            kept.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_max_operand_uses_by_symbol() {
+        use rand::SeedableRng;
+
+        let operand = |symbol: &str, value: f64| Operand {
+            symbol: symbol.to_string(),
+            value,
+        };
+        // 同一个符号 a 出现两次
+        let operands = vec![operand("a", 1.0), operand("b", 2.0), operand("a", 1.0)];
+        let unary_operators = vec![UnaryOperator::new("u".to_string(), |a| -a)];
+        let binary_operators = vec![BinaryOperator::new("+".to_string(), |a, b| a + b)];
+        let config = GenConfig {
+            max_operand_uses: Some(1),
+            ..GenConfig::default()
+        };
+        let uses_of_a = |tokens: &[Token]| tokens.iter().filter(|t| t.symbol() == "a").count();
+
+        let mut count = 0;
+        for tokens in
+            generate_valid_tokens(&operands, &unary_operators, &binary_operators, 3, config).chain(
+                generate_valid_tokens_of_length(
+                    &operands,
+                    &unary_operators,
+                    &binary_operators,
+                    5,
+                    config,
+                ),
+            )
+        {
+            assert!(uses_of_a(&tokens) <= 1, "{}", TokenVec(&tokens));
+            count += 1;
+        }
+        assert!(count > 0);
+        for partition in partitions_by_length(operands.len(), 5) {
+            let _ = visit_partition(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                partition,
+                config,
+                |tokens| {
+                    assert!(uses_of_a(tokens) <= 1, "{}", TokenVec(tokens));
+                    ControlFlow::Continue(())
+                },
+            );
+        }
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..100 {
+            if let Some(tokens) = sample_valid_tokens(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                3,
+                config,
+                &mut rng,
+            ) {
+                assert!(uses_of_a(&tokens) <= 1, "{}", TokenVec(&tokens));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_valid_tokens() {
+        use crate::rpn::is_valid_rpn;
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        let (operands, unary_operators, binary_operators) = crate::opes_data::prepare_opes();
+        let sample = |seed: u64, config: GenConfig| -> Vec<Vec<Token>> {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            (0..200)
+                .filter_map(|_| {
+                    sample_valid_tokens(
+                        &operands,
+                        &unary_operators,
+                        &binary_operators,
+                        8,
+                        config,
+                        &mut rng,
+                    )
+                })
+                .collect()
+        };
+
+        let sequences = sample(7, GenConfig::default());
+        assert_eq!(sequences.len(), 200);
+        for tokens in &sequences {
+            assert!(is_valid_rpn(tokens));
+            let depth = tokens
+                .iter()
+                .filter(|token| matches!(token, Token::Operand(_)))
+                .count();
+            assert!((1..=8).contains(&depth));
+        }
+        // 种子相同时结果相同
+        let strings = |sequences: &[Vec<Token>]| -> Vec<String> {
+            sequences
+                .iter()
+                .map(|tokens| TokenVec(tokens).to_string())
+                .collect()
+        };
+        assert_eq!(
+            strings(&sequences),
+            strings(&sample(7, GenConfig::default()))
+        );
+        assert_ne!(
+            strings(&sequences),
+            strings(&sample(8, GenConfig::default()))
+        );
+
+        // 每个操作数最多使用一次时，超过操作数个数的深度无法构造
+        let config = GenConfig {
+            max_operand_uses: Some(1),
+            ..GenConfig::default()
+        };
+        for tokens in sample(7, config) {
+            let mut symbols: Vec<&str> = tokens
+                .iter()
+                .filter(|token| matches!(token, Token::Operand(_)))
+                .map(Token::symbol)
+                .collect();
+            let count = symbols.len();
+            symbols.sort();
+            symbols.dedup();
+            assert_eq!(symbols.len(), count);
+        }
+    }
+
+    /// 遍历所有划分，收集其中的序列并排序
+    fn visit_all(
+        operands: &[Operand],
+        unary_operators: &[UnaryOperator],
+        binary_operators: &[BinaryOperator],
+        partitions: Vec<Partition>,
+        config: GenConfig,
+    ) -> Vec<String> {
+        let mut visited = Vec::new();
+        for partition in partitions {
+            let flow = visit_partition(
+                operands,
+                unary_operators,
+                binary_operators,
+                partition,
+                config,
+                |tokens| {
+                    visited.push(TokenVec(tokens).to_string());
+                    ControlFlow::Continue(())
+                },
+            );
+            assert_eq!(flow, ControlFlow::Continue(()));
+        }
+        visited.sort();
+        visited
+    }
+
+    /// 整体生成的所有序列，排序后与 `visit_all` 的结果比较
+    fn sorted(sequences: impl Iterator<Item = Vec<Token>>) -> Vec<String> {
+        let mut strings: Vec<String> = sequences
+            .map(|tokens| TokenVec(&tokens).to_string())
+            .collect();
+        strings.sort();
+        strings
+    }
+
+    #[test]
+    fn test_visit_partition_matches_generate_valid_tokens() {
+        let (operands, unary_operators, binary_operators) = crate::opes_data::prepare_opes();
+        // 只保留少量运算符，其中包含可以剪枝的组合 sin(asin(x))、abs(abs(x))
+        let unary_operators: Vec<UnaryOperator> = unary_operators
+            .into_iter()
+            .filter(|operator| ["sin", "asin", "abs"].contains(&operator.symbol.as_str()))
+            .collect();
+        let binary_operators: Vec<BinaryOperator> = binary_operators
+            .into_iter()
+            .filter(|operator| ["+", "/"].contains(&operator.symbol.as_str()))
+            .collect();
+        let config = GenConfig {
+            prune_trivial_unary: true,
+            max_operand_uses: Some(2),
+        };
+        assert_eq!(
+            visit_all(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                partitions_by_depth(operands.len(), 3),
+                config,
+            ),
+            sorted(generate_valid_tokens(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                3,
+                config,
+            ))
+        );
+        assert_eq!(
+            visit_all(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                partitions_by_length(operands.len(), 5),
+                config,
+            ),
+            sorted(generate_valid_tokens_of_length(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                5,
+                config,
+            ))
+        );
+
+        // 回调返回 Break 时立即停止
+        let mut count = 0;
+        let flow = visit_partition(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            Partition::Length {
+                length: 5,
+                first_operand: 0,
+            },
+            config,
+            |_| {
+                count += 1;
+                if count == 10 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        );
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(count, 10);
+    }
+
+    #[test]
+    fn test_partitions_cover_search_space() {
+        let (operands, unary_operators, binary_operators) = crate::opes_data::prepare_opes();
+        let config = GenConfig::default();
+
+        // 所有划分遍历到的序列合起来应与整体生成的结果完全相同
+        assert_eq!(
+            visit_all(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                partitions_by_depth(operands.len(), 2),
+                config,
+            ),
+            sorted(generate_valid_tokens(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                2,
+                config,
+            ))
+        );
+        assert_eq!(
+            visit_all(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                partitions_by_length(operands.len(), 3),
+                config,
+            ),
+            sorted(generate_valid_tokens_of_length(
+                &operands,
+                &unary_operators,
+                &binary_operators,
+                3,
+                config,
+            ))
+        );
+    }
+}
@@ -0,0 +1,215 @@
+// src/infix.rs
+use crate::ast::Expr;
+use crate::rpn::is_valid_rpn;
+use crate::types::Token;
+
+// 以中缀形式书写的二元运算符，其余二元运算符按函数调用形式输出，如 min(a, b)
+const INFIX_OPERATORS: [&str; 6] = ["+", "-", "*", "/", "^", "mod"];
+// 以后缀形式书写的一元运算符，其余一元运算符按函数调用形式输出，如 sin(a)
+const POSTFIX_OPERATORS: [&str; 1] = ["!"];
+
+/// 把RPN表达式转换为中缀表达式字符串
+///
+/// 每个二元运算都会加上括号，不考虑运算符优先级；按优先级省略括号的形式见 `to_infix_minimal`。
+///
+/// # Panics
+///
+/// 如果输入的 `tokens` 不是一个有效的 RPN 序列，该函数会 panic。
+pub fn to_infix(tokens: &[Token]) -> String {
+    assert!(
+        is_valid_rpn(tokens),
+        "Invalid RPN sequence passed to to_infix"
+    );
+
+    let mut stack: Vec<String> = Vec::new(); // 保存已转换的子表达式
+    for token in tokens {
+        match token {
+            Token::Operand(operand) => stack.push(operand.symbol.clone()),
+            Token::UnaryOperator(operator) => {
+                let value = stack.pop().unwrap();
+                if POSTFIX_OPERATORS.contains(&operator.symbol.as_str()) {
+                    stack.push(format!("({}){}", value, operator.symbol));
+                } else {
+                    stack.push(format!("{}({})", operator.symbol, value));
+                }
+            }
+            Token::BinaryOperator(operator) => {
+                let right = stack.pop().unwrap();
+                let left = stack.pop().unwrap();
+                if INFIX_OPERATORS.contains(&operator.symbol.as_str()) {
+                    stack.push(format!("({} {} {})", left, operator.symbol, right));
+                } else {
+                    stack.push(format!("{}({}, {})", operator.symbol, left, right));
+                }
+            }
+        }
+    }
+    stack.pop().unwrap()
+}
+
+// 子表达式的结合强度，数值越大越不需要加括号
+const SUM: u8 = 1; // a + b、a - b，以及负数字面量
+const PRODUCT: u8 = 2; // a * b、a / b、a mod b
+const POWER: u8 = 3; // a ^ b
+const POSTFIX: u8 = 4; // a!
+const ATOM: u8 = 5; // 单个符号或函数调用
+
+/// 中缀二元运算符的优先级和结合性
+struct Precedence {
+    strength: u8,            // 结合强度
+    right_associative: bool, // 是否右结合，只有 `^` 是
+}
+
+fn precedence(symbol: &str) -> Option<Precedence> {
+    let (strength, right_associative) = match symbol {
+        "+" | "-" => (SUM, false),
+        "*" | "/" | "mod" => (PRODUCT, false),
+        "^" => (POWER, true),
+        _ => return None, // 按函数调用形式输出
+    };
+    Some(Precedence {
+        strength,
+        right_associative,
+    })
+}
+
+/// 把RPN表达式转换为只在必要时加括号的中缀表达式字符串
+///
+/// 按运算符的优先级和结合性决定括号：`+ -` 最低，`* / mod` 其次，`^` 最高且右结合，
+/// 如 `e pi + γ *` 写成 `(e + pi) * γ`，`e pi γ * +` 写成 `e + pi * γ`。
+/// 输出可以被 `parse_infix` 解析回相同的RPN序列。
+///
+/// # Panics
+///
+/// 如果输入的 `tokens` 不是一个有效的 RPN 序列，该函数会 panic。
+pub fn to_infix_minimal(tokens: &[Token]) -> String {
+    let expr = Expr::from_rpn(tokens).expect("Invalid RPN sequence passed to to_infix_minimal");
+    render(&expr).0
+}
+
+/// 按需加括号，返回中缀字符串及其结合强度
+fn render(expr: &Expr) -> (String, u8) {
+    match expr {
+        Expr::Const(operand) if operand.symbol.starts_with('-') => (operand.symbol.clone(), SUM),
+        Expr::Const(operand) => (operand.symbol.clone(), ATOM),
+        Expr::Unary(operator, value) => {
+            if POSTFIX_OPERATORS.contains(&operator.symbol.as_str()) {
+                let value = render(value);
+                (
+                    format!("{}{}", parenthesize(value, POSTFIX), operator.symbol),
+                    POSTFIX,
+                )
+            } else {
+                (format!("{}({})", operator.symbol, render(value).0), ATOM)
+            }
+        }
+        Expr::Binary(operator, left, right) => match precedence(&operator.symbol) {
+            Some(Precedence {
+                strength,
+                right_associative,
+            }) => {
+                // 与运算符结合强度相同的一侧只有在结合方向上才可以省略括号
+                let (left_min, right_min) = if right_associative {
+                    (strength + 1, strength)
+                } else {
+                    (strength, strength + 1)
+                };
+                (
+                    format!(
+                        "{} {} {}",
+                        parenthesize(render(left), left_min),
+                        operator.symbol,
+                        parenthesize(render(right), right_min)
+                    ),
+                    strength,
+                )
+            }
+            None => (
+                format!(
+                    "{}({}, {})",
+                    operator.symbol,
+                    render(left).0,
+                    render(right).0
+                ),
+                ATOM,
+            ),
+        },
+    }
+}
+
+/// 结合强度低于 `min` 时给子表达式加括号
+fn parenthesize((infix, strength): (String, u8), min: u8) -> String {
+    if strength < min {
+        format!("({})", infix)
+    } else {
+        infix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{GenConfig, generate_valid_tokens_of_length};
+    use crate::opes_data::prepare_opes;
+    use crate::parser::{parse_infix, parse_rpn};
+    use crate::types::{Operand, TokenVec};
+
+    fn minimal(rpn: &str) -> String {
+        to_infix_minimal(&parse_rpn(rpn).unwrap())
+    }
+
+    #[test]
+    fn test_to_infix_minimal() {
+        assert_eq!(minimal("e pi + γ *"), "(e + pi) * γ");
+        assert_eq!(minimal("e pi γ * +"), "e + pi * γ");
+        assert_eq!(minimal("e pi + γ sin +"), "e + pi + sin(γ)");
+        assert_eq!(minimal("e pi γ / /"), "e / (pi / γ)");
+        assert_eq!(minimal("e pi mod γ *"), "e mod pi * γ");
+        assert_eq!(minimal("e pi + sin"), "sin(e + pi)");
+        assert_eq!(minimal("e pi + γ min 2 *"), "min(e + pi, γ) * 2");
+        assert_eq!(minimal("e pi + !"), "(e + pi)!");
+        assert_eq!(minimal("e pi ! ^"), "e ^ pi!");
+        assert_eq!(minimal("e pi ^ !"), "(e ^ pi)!");
+        // 负数字面量
+        assert_eq!(minimal("-2 e +"), "-2 + e");
+        assert_eq!(minimal("e -2 -"), "e - (-2)");
+        assert_eq!(minimal("-2 2 ^"), "(-2) ^ 2");
+    }
+
+    #[test]
+    fn test_to_infix_minimal_associativity() {
+        // 减法左结合：只有右侧的减法需要括号
+        assert_eq!(minimal("e pi - γ -"), "e - pi - γ");
+        assert_eq!(minimal("e pi γ - -"), "e - (pi - γ)");
+        assert_eq!(minimal("e pi γ + -"), "e - (pi + γ)");
+        // 乘方右结合：只有左侧的乘方需要括号
+        assert_eq!(minimal("e pi 2 ^ ^"), "e ^ pi ^ 2");
+        assert_eq!(minimal("e pi ^ 2 ^"), "(e ^ pi) ^ 2");
+        assert_eq!(minimal("e pi * 2 ^"), "(e * pi) ^ 2");
+    }
+
+    #[test]
+    fn test_to_infix_minimal_round_trip() {
+        // 输出总能被 parse_infix 解析回原来的序列
+        let (mut operands, unary_operators, binary_operators) = prepare_opes();
+        operands.push(Operand {
+            symbol: "-2".to_string(),
+            value: -2.0,
+        });
+        for tokens in generate_valid_tokens_of_length(
+            &operands,
+            &unary_operators,
+            &binary_operators,
+            4,
+            GenConfig::default(),
+        ) {
+            let infix = to_infix_minimal(&tokens);
+            assert_eq!(
+                parse_infix(&infix).map(|parsed| TokenVec(&parsed).to_string()),
+                Ok(TokenVec(&tokens).to_string()),
+                "{}",
+                infix
+            );
+        }
+    }
+}
@@ -0,0 +1,37 @@
+// src/json.rs
+
+/// 把字符串编码为 JSON 字符串字面量
+pub fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string(""), r#""""#);
+        assert_eq!(json_string("pi e +"), r#""pi e +""#);
+        assert_eq!(json_string(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(json_string(r"a\b"), r#""a\\b""#);
+        assert_eq!(json_string("1\n2\r\t"), r#""1\n2\r\t""#);
+        assert_eq!(json_string("\u{0}\u{1f}\u{7f}"), r#""\u0000\u001f\u007f""#);
+        // 非 ASCII 字符原样输出
+        assert_eq!(json_string("γ √2 φ"), r#""γ √2 φ""#);
+    }
+}
@@ -0,0 +1,211 @@
+// src/latex.rs
+use crate::rpn::is_valid_rpn;
+use crate::types::Token;
+
+// 子表达式的结合强度，数值越大越不需要加括号
+const SUM: u8 = 1; // a + b、a - b
+const PRODUCT: u8 = 2; // a \cdot b、a \bmod b
+const POWER: u8 = 3; // a^{b}、a!
+const FUNCTION: u8 = 4; // \sin\left(a\right)、\frac{a}{b} 等自带分组的形式
+const ATOM: u8 = 5; // 单个符号
+
+/// 把RPN表达式转换为 LaTeX 数学公式
+///
+/// 与 `to_infix` 不同，只在运算符优先级需要时才加括号：`/` 写成 `\frac`，
+/// `^` 的指数总是放在花括号中，底数不是单个符号时加括号。
+///
+/// # Panics
+///
+/// 如果输入的 `tokens` 不是一个有效的 RPN 序列，该函数会 panic。
+pub fn to_latex(tokens: &[Token]) -> String {
+    assert!(
+        is_valid_rpn(tokens),
+        "Invalid RPN sequence passed to to_latex"
+    );
+
+    let mut stack: Vec<(String, u8)> = Vec::new(); // 保存已转换的子表达式及其结合强度
+    for token in tokens {
+        match token {
+            Token::Operand(operand) => stack.push(operand_latex(&operand.symbol)),
+            Token::UnaryOperator(operator) => {
+                let value = stack.pop().unwrap();
+                stack.push(unary_latex(&operator.symbol, value));
+            }
+            Token::BinaryOperator(operator) => {
+                let right = stack.pop().unwrap();
+                let left = stack.pop().unwrap();
+                stack.push(binary_latex(&operator.symbol, left, right));
+            }
+        }
+    }
+    stack.pop().unwrap().0
+}
+
+/// 结合强度低于 `min` 时给子表达式加括号
+fn group((latex, strength): (String, u8), min: u8) -> String {
+    if strength < min {
+        format!("\\left({}\\right)", latex)
+    } else {
+        latex
+    }
+}
+
+fn operand_latex(symbol: &str) -> (String, u8) {
+    match symbol {
+        "pi" => (r"\pi".to_string(), ATOM),
+        "γ" => (r"\gamma".to_string(), ATOM),
+        "φ" => (r"\varphi".to_string(), ATOM),
+        "√2" => (r"\sqrt{2}".to_string(), FUNCTION),
+        "ln2" => (r"\ln 2".to_string(), FUNCTION),
+        _ if symbol.starts_with('-') => (escape(symbol), SUM), // 负数字面量
+        _ => (escape(symbol), ATOM),
+    }
+}
+
+fn unary_latex(symbol: &str, value: (String, u8)) -> (String, u8) {
+    let function = |name: &str| format!("{}\\left({}\\right)", name, value.0);
+    let latex = match symbol {
+        "sin" | "cos" | "tan" | "sinh" | "cosh" | "tanh" | "coth" | "cot" | "csc" | "sec"
+        | "ln" => function(&format!("\\{}", symbol)),
+        "asin" | "acos" | "atan" => function(&format!("\\arc{}", &symbol[1..])),
+        "asinh" | "acosh" | "atanh" => function(&format!("\\operatorname{{ar{}}}", &symbol[1..])),
+        "log" => function(r"\log_{10}"),
+        "gamma" => function(r"\Gamma"),
+        "sqrt" => format!("\\sqrt{{{}}}", value.0),
+        "abs" => format!("\\left|{}\\right|", value.0),
+        "floor" => format!("\\left\\lfloor {}\\right\\rfloor", value.0),
+        "ceil" => format!("\\left\\lceil {}\\right\\rceil", value.0),
+        "!" => return (format!("{}!", group(value, ATOM)), POWER),
+        _ => function(&format!("\\operatorname{{{}}}", escape(symbol))),
+    };
+    (latex, FUNCTION)
+}
+
+fn binary_latex(symbol: &str, left: (String, u8), right: (String, u8)) -> (String, u8) {
+    match symbol {
+        "+" => (format!("{} + {}", group(left, SUM), group(right, SUM)), SUM),
+        // 减号右侧的加减法必须加括号
+        "-" => (
+            format!("{} - {}", group(left, SUM), group(right, PRODUCT)),
+            SUM,
+        ),
+        "*" => (
+            format!("{} \\cdot {}", group(left, PRODUCT), group(right, PRODUCT)),
+            PRODUCT,
+        ),
+        "mod" => (
+            format!("{} \\bmod {}", group(left, PRODUCT), group(right, POWER)),
+            PRODUCT,
+        ),
+        "/" => (format!("\\frac{{{}}}{{{}}}", left.0, right.0), FUNCTION),
+        // 指数总在花括号中，底数不是单个符号时加括号，避免 \sqrt{a}^{b}、a^{b}^{c} 之类的歧义
+        "^" => (format!("{}^{{{}}}", group(left, ATOM), right.0), POWER),
+        "logb" => (
+            format!("\\log_{{{}}}\\left({}\\right)", left.0, right.0),
+            FUNCTION,
+        ),
+        "root" => (format!("\\sqrt[{}]{{{}}}", left.0, right.0), FUNCTION),
+        "min" | "max" => (
+            format!("\\{}\\left({}, {}\\right)", symbol, left.0, right.0),
+            FUNCTION,
+        ),
+        _ => (
+            format!(
+                "\\operatorname{{{}}}\\left({}, {}\\right)",
+                escape(symbol),
+                left.0,
+                right.0
+            ),
+            FUNCTION,
+        ),
+    }
+}
+
+/// 转义 LaTeX 中有特殊含义的字符
+fn escape(symbol: &str) -> String {
+    let mut escaped = String::with_capacity(symbol.len());
+    for c in symbol.chars() {
+        match c {
+            '\\' => escaped.push_str(r"\backslash "),
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str(r"\sim "),
+            '^' => escaped.push_str(r"\hat{}"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opes_data::prepare_opes;
+    use crate::types::Operand;
+
+    /// 按空格分隔的符号构造RPN序列，数字作为操作数
+    fn parse(rpn: &str) -> Vec<Token> {
+        let (operands, unary_operators, binary_operators) = prepare_opes();
+        rpn.split_whitespace()
+            .map(|symbol| {
+                if let Some(operand) = operands.iter().find(|operand| operand.symbol == symbol) {
+                    Token::Operand(operand.clone())
+                } else if let Some(operator) = unary_operators.iter().find(|o| o.symbol == symbol) {
+                    Token::UnaryOperator(operator.clone())
+                } else if let Some(operator) = binary_operators.iter().find(|o| o.symbol == symbol)
+                {
+                    Token::BinaryOperator(operator.clone())
+                } else {
+                    Token::Operand(Operand {
+                        symbol: symbol.to_string(),
+                        value: symbol.parse().unwrap(),
+                    })
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_to_latex() {
+        assert_eq!(to_latex(&parse("pi sqrt e 2 ^ +")), r"\sqrt{\pi} + e^{2}");
+        assert_eq!(
+            to_latex(&parse("e pi + γ *")),
+            r"\left(e + \pi\right) \cdot \gamma"
+        );
+        assert_eq!(
+            to_latex(&parse("e pi γ + -")),
+            r"e - \left(\pi + \gamma\right)"
+        );
+        assert_eq!(to_latex(&parse("e pi - γ -")), r"e - \pi - \gamma");
+        assert_eq!(to_latex(&parse("e sin")), r"\sin\left(e\right)");
+        assert_eq!(to_latex(&parse("pi !")), r"\pi!");
+        assert_eq!(to_latex(&parse("e pi + !")), r"\left(e + \pi\right)!");
+    }
+
+    #[test]
+    fn test_to_latex_fractions() {
+        assert_eq!(to_latex(&parse("e pi /")), r"\frac{e}{\pi}");
+        // 嵌套的分数不需要额外的括号
+        assert_eq!(
+            to_latex(&parse("e pi / γ 2 / /")),
+            r"\frac{\frac{e}{\pi}}{\frac{\gamma}{2}}"
+        );
+        assert_eq!(to_latex(&parse("e pi + γ /")), r"\frac{e + \pi}{\gamma}");
+    }
+
+    #[test]
+    fn test_to_latex_exponents() {
+        assert_eq!(to_latex(&parse("e pi 2 + ^")), r"e^{\pi + 2}");
+        // 幂的底数不是单个符号时加括号
+        assert_eq!(to_latex(&parse("e pi ^ 2 ^")), r"\left(e^{\pi}\right)^{2}");
+        assert_eq!(to_latex(&parse("e pi 2 ^ ^")), r"e^{\pi^{2}}");
+        assert_eq!(
+            to_latex(&parse("e pi / 2 ^")),
+            r"\left(\frac{e}{\pi}\right)^{2}"
+        );
+        assert_eq!(to_latex(&parse("e sqrt 2 ^")), r"\left(\sqrt{e}\right)^{2}");
+        assert_eq!(to_latex(&parse("-1 2 ^")), r"\left(-1\right)^{2}");
+    }
+}
@@ -0,0 +1,23 @@
+#![cfg_attr(feature = "precision", feature(f128))] // 高精度计算使用 f128
+
+pub mod ast;
+pub mod batch;
+pub mod compile;
+pub mod dedup;
+pub mod fit;
+pub mod generator;
+pub mod infix;
+pub mod json;
+pub mod latex;
+pub mod opes_data;
+pub mod parser;
+pub mod rank;
+pub mod real;
+pub mod rpn;
+pub mod solve;
+pub mod types;
+pub mod ulp;
+#[cfg(feature = "pyo3")]
+mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
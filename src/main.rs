@@ -1,33 +1,58 @@
-#![feature(float_gamma)] // 启用浮点数gamma函数特性
+#![cfg_attr(feature = "precision", feature(f128))] // 高精度复核使用 f128
 
 mod cli;
-mod generator;
-mod opes_data;
-mod rpn;
-mod types;
+mod output;
+#[cfg(feature = "precision")]
+mod precise;
+mod repl;
+mod stats;
+
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use clap::Parser;
 use crossbeam_channel::bounded;
-use itertools::Itertools; // 导入迭代器工具集
-use tracing::info; // 导入并行迭代器支持
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*; // 导入并行迭代器支持
 
 use crate::cli::Args;
-use crate::generator::*;
-use crate::rpn::*;
-use crate::types::*;
+use crate::output::{LogFormat, OutputFormat, Reporter};
+use crate::stats::{Candidate, ErrorHistogram, SearchStats};
+use tf_solver::batch::{self, BatchTarget, nearest_target};
+use tf_solver::dedup::{SequenceSet, ValueSet};
+use tf_solver::fit;
+use tf_solver::generator::*;
+use tf_solver::opes_data;
+use tf_solver::rank::{self, Complexity, uses_all_symbols};
+use tf_solver::rpn::*;
+use tf_solver::types::*;
+use tf_solver::ulp::within_ulps;
+
+// 提前停止搜索的原因，由最先设置停止标志的一方记录
+const STOP_NONE: u8 = 0;
+const STOP_TIMEOUT: u8 = 1;
+const STOP_INTERRUPTED: u8 = 2;
+
+/// 设置停止标志，若此前还没有停止则记录原因 `reason`
+fn request_stop(stop: &AtomicBool, stop_reason: &AtomicU8, reason: u8) {
+    // 只有第一个原因生效，之后的超时或 Ctrl-C 不会覆盖它
+    let _ = stop_reason.compare_exchange(STOP_NONE, reason, Ordering::Relaxed, Ordering::Relaxed);
+    stop.store(true, Ordering::Relaxed);
+}
 
 // 主函数
 fn main() {
     let args = Args::parse();
-    let file_appender = if let Some(output_file) = &args.output {
-        Some(tracing_appender::rolling::RollingFileAppender::new(
+    let file_appender = args.output.as_ref().map(|output_file| {
+        tracing_appender::rolling::RollingFileAppender::new(
             tracing_appender::rolling::Rotation::NEVER,
             ".",         // 文件夹
             output_file, // 文件路径
-        ))
-    } else {
-        None
-    };
+        )
+    });
 
     if let Some(num_threads) = args.num_threads {
         rayon::ThreadPoolBuilder::default()
@@ -37,67 +62,407 @@ fn main() {
     }
 
     // 初始化日志订阅器
-    let subscriber = tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .with_target(false)
-        .with_thread_ids(true)
-        .with_file(true)
-        .with_line_number(true)
-        .with_ansi(false)
-        .with_writer(std::io::stdout);
-
-    if let Some(appender) = file_appender {
-        subscriber.with_writer(appender).init();
+    if args.log_format == LogFormat::Json {
+        // 每个事件一行 JSON，匹配结果的 rpn、value、target、error 位于 "fields" 中
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_max_level(tracing::Level::INFO)
+            .with_target(false)
+            .with_thread_ids(true)
+            .with_current_span(false)
+            .with_span_list(false);
+
+        // 与文本日志相同：未指定文件时只有 human 格式写到标准输出
+        match file_appender {
+            Some(appender) => subscriber.with_writer(appender).init(),
+            None if args.format == OutputFormat::Human => {
+                subscriber.with_writer(std::io::stdout).init()
+            }
+            None => subscriber.with_writer(std::io::sink).init(),
+        }
+    } else if args.format == OutputFormat::Human {
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::INFO)
+            .with_target(false)
+            .with_thread_ids(true)
+            .with_file(true)
+            .with_line_number(true)
+            .with_ansi(false)
+            .with_writer(std::io::stdout);
+
+        if let Some(appender) = file_appender {
+            subscriber.with_writer(appender).init();
+        } else {
+            subscriber.init();
+        }
     } else {
-        subscriber.init();
+        // 结构化格式下日志只保留输出行本身，使 --output 文件可以直接被解析；
+        // 未指定文件时不再重复写到标准输出
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::INFO)
+            .without_time()
+            .with_level(false)
+            .with_target(false)
+            .with_ansi(false);
+
+        if let Some(appender) = file_appender {
+            subscriber.with_writer(appender).init();
+        } else {
+            subscriber.with_writer(std::io::sink).init();
+        }
     }
 
     // 从 opes 模块获取操作数和运算符
-    let (operands, unary_operators, binary_operators) = opes_data::prepare_opes();
+    let (_, unary_operators, binary_operators) = opes_data::prepare_opes();
+    let mut operands = match opes_data::select_constants(&args.constants) {
+        Ok(operands) => operands,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(2);
+        }
+    };
+
+    if args.repl {
+        repl::run(&operands, &unary_operators, &binary_operators);
+        return;
+    }
+    if let Some(expression) = &args.trace {
+        if let Err(err) = repl::trace(expression, &operands, &unary_operators, &binary_operators) {
+            eprintln!("Error: {}", err);
+            std::process::exit(2);
+        }
+        return;
+    }
+
+    // 拟合模式下加入自变量 x，并把每个表达式的误差平方和当作它的“数值”，目标值为0
+    let samples = args.fit.as_ref().map(|path| match fit::load_samples(path) {
+        Ok(samples) => samples,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(2);
+        }
+    });
+    if samples.is_some() {
+        operands.push(fit::variable());
+    }
+    // --must-use 中的常数名称换成符号，并检查每个符号都可能出现在表达式中
+    let must_use = match rank::resolve_symbols(
+        &args.must_use,
+        &operands,
+        &unary_operators,
+        &binary_operators,
+    ) {
+        Ok(symbols) => symbols,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(2);
+        }
+    };
+    // 批量模式下读取所有目标，否则只有命令行中的一个（拟合模式下为0）
+    let targets = match &args.batch {
+        Some(path) => match batch::load_batch(path) {
+            Ok(targets) => targets,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(2);
+            }
+        },
+        None => vec![BatchTarget {
+            name: String::new(),
+            target: args.target.unwrap_or(0.0),
+            tolerance: None,
+        }],
+    };
 
     let max_depth = args.max_depth; // 设置最大深度
-    let valid_tokens = generate_valid_tokens(
-        &operands[..],
-        &unary_operators[..],
-        &binary_operators[..],
-        max_depth,
-    );
+    let config = GenConfig {
+        prune_trivial_unary: args.prune_trivial_unary,
+        max_operand_uses: args.max_operand_uses,
+    };
+    // 把搜索空间划分为多个部分，由 rayon 线程池并行生成
+    let partitions = match args.length {
+        Some(length) => partitions_by_length(operands.len(), length),
+        None => partitions_by_depth(operands.len(), max_depth),
+    };
+
+    // 随机采样模式下按 --chunk-size 把 N 个样本分成若干块，每块相当于一个划分
+    let blocks = match args.sample {
+        Some(sample) => sample.div_ceil(args.chunk_size.max(1) as u64) as usize,
+        None => partitions.len(),
+    };
 
     let num_threads = args.num_threads.unwrap_or(num_cpus::get());
     let channel_capacity = num_threads * 4;
     let (sender, receiver) = bounded::<Vec<Vec<Token>>>(channel_capacity);
 
+    // 开启 --sort-simple 或 --deterministic 时先缓存所有匹配结果，结束后排序输出
+    let buffer_results = args.sort_simple || args.deterministic;
+    let buffered = Mutex::new(Vec::new());
+    // 每个目标一个 Reporter，各自计算误差并记录已输出的数值
+    let reporters: Vec<Reporter> = targets
+        .iter()
+        .map(|target| Reporter {
+            format: args.format,
+            target: target.target,
+            // 开启 --unique-values 时记录已经输出过的数值
+            unique: args
+                .unique_values
+                .then(|| ValueSet::new(args.unique_epsilon)),
+            show_error: args.show_error,
+            precision: args.precision as usize,
+            simplify: args.simplify,
+            structured_log: args.format == OutputFormat::Human
+                || args.log_format == LogFormat::Json,
+            name: args.batch.is_some().then(|| target.name.clone()),
+        })
+        .collect();
+    if let Some(reporter) = reporters.first() {
+        reporter.header();
+    }
+
+    // 停止标志：超时或按下 Ctrl-C 后设置，生成线程和工作线程在处理每个块之前检查
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_reason = Arc::new(AtomicU8::new(STOP_NONE));
+    if let Some(timeout) = args.timeout {
+        let stop = Arc::clone(&stop);
+        let stop_reason = Arc::clone(&stop_reason);
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_secs_f64(timeout));
+            request_stop(&stop, &stop_reason, STOP_TIMEOUT);
+        });
+    }
+    // Ctrl-C 同样设置停止标志，工作线程处理完手上的块后退出；再次按下时立即结束进程
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let stop = Arc::clone(&stop);
+        let stop_reason = Arc::clone(&stop_reason);
+        let interrupted = Arc::clone(&interrupted);
+        ctrlc::set_handler(move || {
+            if interrupted.swap(true, Ordering::Relaxed) {
+                std::process::exit(130);
+            }
+            request_stop(&stop, &stop_reason, STOP_INTERRUPTED);
+        })
+        .expect("failed to install Ctrl-C handler");
+    }
+    let stats = SearchStats::default();
+    // 开启 --dedup-exact 时记录所有已计算的序列
+    let sequences = args.dedup_exact.then(SequenceSet::new);
+    // 开启 --high-precision 时用 f128 复核每个候选表达式
+    #[cfg(feature = "precision")]
+    let verifier = args.high_precision.then(precise::Verifier::new);
+
     crossbeam::scope(|s| {
         for _ in 0..num_threads {
             let receiver_clone = receiver.clone();
+            let args = &args;
+            let buffered = &buffered;
+            let targets = &targets;
+            let reporters = &reporters;
+            let samples = samples.as_deref();
+            let must_use = &must_use;
+            let stop = &stop;
+            let stats = &stats;
+            let sequences = sequences.as_ref();
+            #[cfg(feature = "precision")]
+            let verifier = verifier.as_ref();
             s.spawn(move |_| {
                 while let Ok(chunk) = receiver_clone.recv() {
+                    if stop.load(Ordering::Relaxed) {
+                        break; // 退出时丢弃接收端，阻塞在发送上的生成线程会随之返回
+                    }
+                    stats
+                        .examined
+                        .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                    let mut best = None; // 本块中最接近目标值的表达式
+                    let mut histogram = ErrorHistogram::default(); // 本块的误差直方图
+                    let mut evaluated = 0;
                     chunk
                         .into_iter()
-                        .filter(|tokens| (calculate(&tokens) - args.target).abs() < args.tolerance) // 筛选结果接近613的表达式
-                        .for_each(|tokens| {
-                            println!("{}: {}", TokenVec(&tokens), calculate(&tokens));
-                            info!("{}: {}", TokenVec(&tokens), calculate(&tokens));
+                        .filter(|tokens| {
+                            // 在计算之前剔除没有用到全部 --must-use 符号或已经计算过的表达式
+                            uses_all_symbols(tokens, must_use)
+                                && sequences.is_none_or(|sequences| sequences.insert(tokens))
+                        })
+                        .map(|tokens| {
+                            let value = match samples {
+                                Some(samples) => {
+                                    fit::sum_squared_error(&tokens, samples, args.max_magnitude)
+                                }
+                                None => calculate_with_limit(&tokens, args.max_magnitude),
+                            };
+                            // 批量模式下按最接近的目标值统计误差
+                            let target = nearest_target(targets, value).unwrap_or(0.0);
+                            Candidate::offer(&mut best, &tokens, value, target);
+                            histogram.record((value - target).abs());
+                            evaluated += 1;
+                            (tokens, value)
+                        })
+                        .for_each(|(tokens, value)| {
+                            for (row, target) in targets.iter().enumerate() {
+                                let tolerance = target.tolerance.unwrap_or(args.tolerance);
+                                let target = target.target;
+                                #[cfg(feature = "precision")]
+                                let value = match verifier {
+                                    Some(verifier) => {
+                                        match verifier.verify(&tokens, value, target, tolerance) {
+                                            Some(value) => value,
+                                            None => continue,
+                                        }
+                                    }
+                                    None => value,
+                                };
+                                // 筛选结果接近目标值的表达式，精确模式下比较ULP距离
+                                let matched = if args.exact {
+                                    within_ulps(value, target, args.max_ulps)
+                                } else {
+                                    (value - target).abs() < tolerance
+                                };
+                                if matched {
+                                    stats.matched.fetch_add(1, Ordering::Relaxed);
+                                    // 过短的表达式仍计入统计，只是不输出
+                                    if tokens.len() < args.min_tokens {
+                                        continue;
+                                    }
+                                    if buffer_results {
+                                        let complexity = Complexity::new(&tokens, value, target);
+                                        buffered.lock().unwrap().push((
+                                            row,
+                                            complexity,
+                                            tokens.clone(),
+                                            value,
+                                        ));
+                                    } else {
+                                        reporters[row].report(&tokens, value);
+                                    }
+                                }
+                            }
                         });
+                    stats.evaluated.fetch_add(evaluated, Ordering::Relaxed);
+                    stats.merge_best(best);
+                    stats.merge_histogram(&histogram);
                 }
             });
         }
         drop(receiver);
 
-        for chunk in &valid_tokens.chunks(args.chunk_size) {
-            if sender.send(chunk.collect()).is_err() {
-                eprintln!("Error sending chunk: Channel closed.");
-                break;
-            }
+        if let Some(sample) = args.sample {
+            // 随机采样：每个块使用同一种子下的独立随机流，结果与线程数和调度无关
+            let chunk_size = args.chunk_size.max(1) as u64;
+            (0..blocks as u64).into_par_iter().for_each(|block| {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let mut rng = ChaCha8Rng::seed_from_u64(args.seed);
+                rng.set_stream(block);
+                let count = chunk_size.min(sample - block * chunk_size);
+                let chunk: Vec<Vec<Token>> = (0..count)
+                    .filter_map(|_| {
+                        sample_valid_tokens(
+                            &operands,
+                            &unary_operators,
+                            &binary_operators,
+                            max_depth,
+                            config,
+                            &mut rng,
+                        )
+                    })
+                    .collect();
+                if sender.send(chunk).is_err() {
+                    if !stop.load(Ordering::Relaxed) {
+                        eprintln!("Error sending chunk: Channel closed.");
+                    }
+                    return;
+                }
+                stats.partitions_done.fetch_add(1, Ordering::Relaxed);
+            });
+        } else {
+            partitions.par_iter().for_each(|&partition| {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                // 发送一块序列，已停止或通道已关闭时返回 Break
+                let send = |chunk: Vec<Vec<Token>>| {
+                    if stop.load(Ordering::Relaxed) {
+                        return ControlFlow::Break(());
+                    }
+                    if sender.send(chunk).is_err() {
+                        if !stop.load(Ordering::Relaxed) {
+                            eprintln!("Error sending chunk: Channel closed.");
+                        }
+                        return ControlFlow::Break(());
+                    }
+                    ControlFlow::Continue(())
+                };
+                // 边生成边分块发送，通道有界，内存占用与搜索深度和划分大小无关
+                let chunk_size = args.chunk_size.max(1);
+                let mut chunk = Vec::with_capacity(chunk_size);
+                let flow = visit_partition(
+                    &operands,
+                    &unary_operators,
+                    &binary_operators,
+                    partition,
+                    config,
+                    |tokens| {
+                        chunk.push(tokens.to_vec());
+                        if chunk.len() < chunk_size {
+                            return ControlFlow::Continue(());
+                        }
+                        send(std::mem::replace(
+                            &mut chunk,
+                            Vec::with_capacity(chunk_size),
+                        ))
+                    },
+                );
+                if flow.is_break() || (!chunk.is_empty() && send(chunk).is_break()) {
+                    return;
+                }
+                stats.partitions_done.fetch_add(1, Ordering::Relaxed);
+            });
         }
+        drop(sender); // 关闭通道，让工作线程在处理完剩余数据后退出
     })
     .unwrap();
+
+    let mut buffered = buffered.into_inner().unwrap();
+    // 先按目标，再按复杂度（仅 --sort-simple）和RPN字符串排序，使输出按目标分组且与线程调度无关
+    buffered.sort_by_cached_key(|(row, complexity, tokens, _)| {
+        (
+            *row,
+            args.sort_simple.then_some(*complexity),
+            TokenVec(tokens).to_string(),
+        )
+    });
+    // 排序后再去重，数值相同时保留最简洁的表达式
+    for (row, _, tokens, value) in buffered {
+        reporters[row].report(&tokens, value);
+    }
+
+    match stop_reason.load(Ordering::Relaxed) {
+        STOP_TIMEOUT => eprintln!("Timeout reached, search stopped early."),
+        STOP_INTERRUPTED => eprintln!("Interrupted, search stopped early."),
+        _ => {}
+    }
+    stats.print_summary(blocks);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::rpn::calculate;
-    use crate::types::{BinaryOperator, Operand, Token, UnaryOperator};
+    use tf_solver::rpn::calculate;
+    use tf_solver::types::{BinaryOperator, Operand, Token, UnaryOperator};
+
+    #[test]
+    fn test_request_stop_keeps_first_reason() {
+        use super::{STOP_INTERRUPTED, STOP_NONE, STOP_TIMEOUT, request_stop};
+        use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+        let (stop, stop_reason) = (AtomicBool::new(false), AtomicU8::new(STOP_NONE));
+        request_stop(&stop, &stop_reason, STOP_TIMEOUT);
+        // 超时之后紧接着按下 Ctrl-C，仍报告超时
+        request_stop(&stop, &stop_reason, STOP_INTERRUPTED);
+        assert!(stop.load(Ordering::Relaxed));
+        assert_eq!(stop_reason.load(Ordering::Relaxed), STOP_TIMEOUT);
+    }
 
     #[test]
     fn test_calculate() {
@@ -158,4 +523,19 @@ mod tests {
         // Fix the assertion for sqrt(5.0)
         assert!((calculate(&tokens4) - 5.0_f64.sqrt()).abs() < 1e-9);
     }
+
+    #[test]
+    fn test_calculate_out_of_domain() {
+        let tokens = vec![
+            Token::Operand(Operand {
+                symbol: "2".to_string(),
+                value: 2.0,
+            }),
+            Token::UnaryOperator(
+                UnaryOperator::new("asin".to_string(), |a| a.asin())
+                    .with_domain(|a| a.abs() <= 1.0),
+            ),
+        ];
+        assert!(calculate(&tokens).is_nan()); // asin 的定义域为 [-1, 1]
+    }
 }
@@ -1,69 +1,207 @@
-use crate::types::{BinaryOperator, Operand, UnaryOperator};
-use std::f64::consts::{E, PI};
+use crate::real::Real;
+use crate::types::{BinaryOperator, BinaryOperatorOf, Operand, UnaryOperator, UnaryOperatorOf};
+use std::f64::consts::{E, LN_2, PI, SQRT_2};
+
+/// 相邻出现时没有意义的一元运算符对 `(内层, 外层)`
+///
+/// 外层紧跟在内层之后要么抵消内层（如 `sin(asin(x))`），要么与只用内层的结果相同
+/// （如 `abs(abs(x))`、`floor(ceil(x))`）。`asin(sin(x))` 这类只在主值区间内
+/// 才是恒等变换的组合不在其中，因为它们仍可能得到不同的数值。
+pub const TRIVIAL_UNARY_PAIRS: &[(&str, &str)] = &[
+    ("asin", "sin"),
+    ("acos", "cos"),
+    ("atan", "tan"),
+    ("asinh", "sinh"),
+    ("acosh", "cosh"),
+    ("atanh", "tanh"),
+    ("sinh", "asinh"),
+    ("tanh", "atanh"),
+    ("abs", "abs"),
+    ("sqrt", "abs"),
+    ("cosh", "abs"),
+    ("floor", "floor"),
+    ("ceil", "ceil"),
+    ("floor", "ceil"),
+    ("ceil", "floor"),
+];
+
+/// 可作为操作数使用的数学常数
+pub struct Constant {
+    pub name: &'static str,   // 命令行中使用的名称
+    pub symbol: &'static str, // 表达式中显示的符号
+    pub value: f64,           // 常数的数值
+}
+
+/// 所有可用的数学常数
+#[allow(clippy::excessive_precision)]
+pub const CONSTANTS: &[Constant] = &[
+    Constant {
+        name: "e",
+        symbol: "e",
+        value: E,
+    },
+    Constant {
+        name: "pi",
+        symbol: "pi",
+        value: PI,
+    },
+    Constant {
+        name: "euler",
+        symbol: "γ",
+        value: 0.57721566490153286060651209,
+    },
+    Constant {
+        name: "phi",
+        symbol: "φ",
+        value: 1.61803398874989484820458683,
+    },
+    Constant {
+        name: "sqrt2",
+        symbol: "√2",
+        value: SQRT_2,
+    },
+    Constant {
+        name: "ln2",
+        symbol: "ln2",
+        value: LN_2,
+    },
+    Constant {
+        name: "catalan",
+        symbol: "G",
+        value: 0.91596559417721901505460351,
+    },
+];
+
+/// 默认启用的常数：e、π 和欧拉常数 γ
+pub const DEFAULT_CONSTANTS: &[&str] = &["e", "pi", "euler"];
+
+/// 按名称或符号从常数表中选出操作数
+pub fn select_constants<S: AsRef<str>>(names: &[S]) -> Result<Vec<Operand>, String> {
+    names
+        .iter()
+        .map(|name| {
+            let name = name.as_ref();
+            CONSTANTS
+                .iter()
+                .find(|constant| constant.name == name || constant.symbol == name)
+                .map(|constant| Operand {
+                    symbol: constant.symbol.to_string(),
+                    value: constant.value,
+                })
+                .ok_or_else(|| format!("unknown constant: {}", name))
+        })
+        .collect()
+}
 
 pub fn prepare_opes() -> (Vec<Operand>, Vec<UnaryOperator>, Vec<BinaryOperator>) {
-    // 定义基本操作数：e和π
-    let operands = vec![
-        Operand {
-            symbol: "e".to_string(),
-            value: E,
-        },
-        Operand {
-            symbol: "pi".to_string(),
-            value: PI,
-        },
-        Operand {
-            symbol: "γ".to_string(),
-            value: 0.57721566490153286060651209,
-        },
-    ];
+    // 定义基本操作数：e、π 和 γ
+    let operands = select_constants(DEFAULT_CONSTANTS).unwrap();
+    let (unary_operators, binary_operators) = operators::<f64>();
 
+    (operands, unary_operators, binary_operators)
+}
+
+/// 以数值类型 `R` 构造所有可用的运算符
+///
+/// `prepare_opes` 使用其中的 `f64` 版本；高精度复核时按符号在对应类型的表中查找同名运算符。
+pub fn operators<R: Real>() -> (Vec<UnaryOperatorOf<R>>, Vec<BinaryOperatorOf<R>>) {
     // 定义所有可用的一元运算符
     let unary_operators = vec![
-        UnaryOperator::new("sin".to_string(), |a| a.sin()),
-        UnaryOperator::new("cos".to_string(), |a| a.cos()),
-        UnaryOperator::new("tan".to_string(), |a| a.tan()),
-        UnaryOperator::new("asin".to_string(), |a| a.asin()),
-        UnaryOperator::new("acos".to_string(), |a| a.acos()),
-        UnaryOperator::new("atan".to_string(), |a| a.atan()),
-        UnaryOperator::new("sinh".to_string(), |a| a.sinh()),
-        UnaryOperator::new("cosh".to_string(), |a| a.cosh()),
-        UnaryOperator::new("tanh".to_string(), |a| a.tanh()),
-        UnaryOperator::new("asinh".to_string(), |a| a.asinh()),
-        UnaryOperator::new("acosh".to_string(), |a| a.acosh()),
-        UnaryOperator::new("atanh".to_string(), |a| a.atanh()),
-        UnaryOperator::new("coth".to_string(), |a| a.cosh() / a.sinh()),
-        UnaryOperator::new("csch".to_string(), |a| 1.0 / a.sinh()),
-        UnaryOperator::new("sech".to_string(), |a| 1.0 / a.cosh()),
-        UnaryOperator::new("cot".to_string(), |a| a.cos() / a.sin()),
-        UnaryOperator::new("csc".to_string(), |a| 1.0 / a.sin()),
-        UnaryOperator::new("sec".to_string(), |a| 1.0 / a.cos()),
-        UnaryOperator::new(
-            "sinc".to_string(),
-            |a| if a == 0.0 { 1.0 } else { a.sin() / a },
-        ),
-        UnaryOperator::new("sqrt".to_string(), |a| a.sqrt()),
-        UnaryOperator::new("abs".to_string(), |a| a.abs()),
-        UnaryOperator::new("ln".to_string(), |a| a.ln()),
-        UnaryOperator::new("log".to_string(), |a| a.log10()),
-        UnaryOperator::new("gamma".to_string(), |a| a.gamma()),
-        UnaryOperator::new("!".to_string(), |a| (a - 1.0).gamma()),
-        UnaryOperator::new("floor".to_string(), |a| a.floor()),
-        UnaryOperator::new("ceil".to_string(), |a| a.ceil()),
+        UnaryOperatorOf::<R>::new("sin".to_string(), |a| a.sin()),
+        UnaryOperatorOf::<R>::new("cos".to_string(), |a| a.cos()),
+        UnaryOperatorOf::<R>::new("tan".to_string(), |a| a.tan()),
+        UnaryOperatorOf::<R>::new("asin".to_string(), |a| a.asin())
+            .with_domain(|a| a.abs() <= R::ONE),
+        UnaryOperatorOf::<R>::new("acos".to_string(), |a| a.acos())
+            .with_domain(|a| a.abs() <= R::ONE),
+        UnaryOperatorOf::<R>::new("atan".to_string(), |a| a.atan()),
+        UnaryOperatorOf::<R>::new("sinh".to_string(), |a| a.sinh()),
+        UnaryOperatorOf::<R>::new("cosh".to_string(), |a| a.cosh()),
+        UnaryOperatorOf::<R>::new("tanh".to_string(), |a| a.tanh()),
+        UnaryOperatorOf::<R>::new("asinh".to_string(), |a| a.asinh()),
+        UnaryOperatorOf::<R>::new("acosh".to_string(), |a| a.acosh()).with_domain(|a| a >= R::ONE),
+        UnaryOperatorOf::<R>::new("atanh".to_string(), |a| a.atanh())
+            .with_domain(|a| a.abs() < R::ONE),
+        UnaryOperatorOf::<R>::new("coth".to_string(), |a| a.cosh() / a.sinh())
+            .with_domain(|a| a != R::ZERO),
+        UnaryOperatorOf::<R>::new("csch".to_string(), |a| R::ONE / a.sinh())
+            .with_domain(|a| a != R::ZERO),
+        UnaryOperatorOf::<R>::new("sech".to_string(), |a| R::ONE / a.cosh()),
+        UnaryOperatorOf::<R>::new("cot".to_string(), |a| a.cos() / a.sin())
+            .with_domain(|a| a.sin() != R::ZERO),
+        UnaryOperatorOf::<R>::new("csc".to_string(), |a| R::ONE / a.sin())
+            .with_domain(|a| a.sin() != R::ZERO),
+        UnaryOperatorOf::<R>::new("sec".to_string(), |a| R::ONE / a.cos()),
+        UnaryOperatorOf::<R>::new("sinc".to_string(), |a| {
+            if a == R::ZERO { R::ONE } else { a.sin() / a }
+        }),
+        UnaryOperatorOf::<R>::new("sqrt".to_string(), |a| a.sqrt()).with_domain(|a| a >= R::ZERO),
+        UnaryOperatorOf::<R>::new("abs".to_string(), |a| a.abs()),
+        UnaryOperatorOf::<R>::new("ln".to_string(), |a| a.ln()).with_domain(|a| a > R::ZERO),
+        UnaryOperatorOf::<R>::new("log".to_string(), |a| a.log10()).with_domain(|a| a > R::ZERO),
+        UnaryOperatorOf::<R>::new("gamma".to_string(), |a| a.gamma()).with_domain(not_gamma_pole),
+        UnaryOperatorOf::<R>::new("!".to_string(), |a| (a - R::ONE).gamma())
+            .with_domain(|a| not_gamma_pole(a - R::ONE)),
+        UnaryOperatorOf::<R>::new("floor".to_string(), |a| a.floor()),
+        UnaryOperatorOf::<R>::new("ceil".to_string(), |a| a.ceil()),
     ];
 
     // 定义所有可用的二元运算符
     let binary_operators = vec![
-        BinaryOperator::new("+".to_string(), |a, b| a + b),
-        BinaryOperator::new("-".to_string(), |a, b| a - b),
-        BinaryOperator::new("*".to_string(), |a, b| a * b),
-        BinaryOperator::new("/".to_string(), |a, b| a / b),
-        BinaryOperator::new("^".to_string(), |a, b| a.powf(b)),
-        BinaryOperator::new("mod".to_string(), |a, b| a % b),
-        BinaryOperator::new("min".to_string(), |a, b| a.min(b)),
-        BinaryOperator::new("max".to_string(), |a, b| a.max(b)),
-        BinaryOperator::new("atan2".to_string(), |a, b| a.atan2(b)),
+        BinaryOperatorOf::<R>::new("+".to_string(), |a, b| a + b),
+        BinaryOperatorOf::<R>::new("-".to_string(), |a, b| a - b),
+        BinaryOperatorOf::<R>::new("*".to_string(), |a, b| a * b),
+        BinaryOperatorOf::<R>::new("/".to_string(), |a, b| a / b),
+        BinaryOperatorOf::<R>::new("^".to_string(), |a, b| a.powf(b)),
+        BinaryOperatorOf::<R>::new("mod".to_string(), |a, b| a % b),
+        BinaryOperatorOf::<R>::new("min".to_string(), |a, b| a.min(b)),
+        BinaryOperatorOf::<R>::new("max".to_string(), |a, b| a.max(b)),
+        BinaryOperatorOf::<R>::new("atan2".to_string(), |a, b| a.atan2(b)),
+        // 以 a 为底 b 的对数
+        BinaryOperatorOf::<R>::new("logb".to_string(), |a, b| b.log(a))
+            .with_domain(|a, b| a > R::ZERO && a != R::ONE && b > R::ZERO),
+        // b 的 a 次方根
+        BinaryOperatorOf::<R>::new("root".to_string(), |a, b| b.powf(R::ONE / a))
+            .with_domain(|a, b| a != R::ZERO && b >= R::ZERO),
     ];
 
-    (operands, unary_operators, binary_operators)
+    (unary_operators, binary_operators)
+}
+
+/// gamma 函数在非正整数处没有定义
+fn not_gamma_pole<R: Real>(a: R) -> bool {
+    a > R::ZERO || a.fract() != R::ZERO
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_rpn;
+    use crate::rpn::{calculate, calculate_checked};
+
+    fn evaluate(text: &str) -> Option<f64> {
+        calculate_checked(&parse_rpn(text).unwrap())
+    }
+
+    #[test]
+    fn test_logb() {
+        assert_eq!(evaluate("2 8 logb"), Some(3.0));
+        assert_eq!(evaluate("2 0.125 logb"), Some(-3.0));
+        // 底数为1、底数不为正、真数不为正
+        assert!(calculate(&parse_rpn("1 8 logb").unwrap()).is_nan());
+        assert_eq!(evaluate("0 8 logb"), None);
+        assert_eq!(evaluate("-2 8 logb"), None);
+        assert_eq!(evaluate("2 0 logb"), None);
+        assert_eq!(evaluate("2 -8 logb"), None);
+    }
+
+    #[test]
+    fn test_root() {
+        assert_eq!(evaluate("3 27 root"), Some(3.0));
+        assert_eq!(evaluate("2 0 root"), Some(0.0));
+        assert_eq!(evaluate("-2 4 root"), Some(0.5));
+        // 负数的方根和0次方根
+        assert!(calculate(&parse_rpn("2 -4 root").unwrap()).is_nan());
+        assert_eq!(evaluate("3 -27 root"), None);
+        assert_eq!(evaluate("0 27 root"), None);
+    }
 }
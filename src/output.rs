@@ -0,0 +1,274 @@
+// src/output.rs
+use clap::ValueEnum;
+use tracing::info;
+
+use tf_solver::ast::Expr;
+use tf_solver::dedup::ValueSet;
+use tf_solver::infix::to_infix_minimal;
+use tf_solver::json::json_string;
+use tf_solver::latex::to_latex;
+use tf_solver::types::{Token, TokenVec};
+
+/// 匹配结果的输出格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// `表达式: 数值`
+    Human,
+    /// `expression,value,error`，带表头；--show-error 时增加 `rel_error` 列，
+    /// --batch 时在最前面增加 `name` 列
+    Csv,
+    /// 每行一个 JSON 对象：`{"rpn", "infix", "value", "target", "error"}`，
+    /// --show-error 时增加 `"rel_error"`，--batch 时增加 `"name"`
+    Jsonl,
+    /// `LaTeX公式 \approx 数值`，可直接放入数学环境
+    Latex,
+}
+
+/// 日志文件的格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// 文本日志；--format human 时每个匹配结果记录为带 `rpn`、`value`、`target`、`error`
+    /// 字段的事件，其他输出格式只记录输出行本身
+    Text,
+    /// 每行一个 JSON 对象，匹配结果的各个字段位于 `"fields"` 中
+    Json,
+}
+
+/// 负责把匹配结果按指定格式输出到标准输出和日志
+pub struct Reporter {
+    pub format: OutputFormat,     // 输出格式
+    pub target: f64,              // 目标值，用于计算误差
+    pub unique: Option<ValueSet>, // 开启 --unique-values 时记录已输出的数值
+    pub show_error: bool,         // 是否额外输出误差和相对误差
+    pub precision: usize,         // 数值和误差保留的有效数字位数
+    pub simplify: bool,           // 是否输出化简后的表达式
+    pub structured_log: bool,     // 日志中以结构化字段记录匹配结果，而不是输出行本身
+    pub name: Option<String>,     // --batch 中目标的名称，用于标记输出结果
+}
+
+impl Reporter {
+    /// 输出表头（如果该格式需要）
+    pub fn header(&self) {
+        let header = match self.format {
+            OutputFormat::Human | OutputFormat::Jsonl | OutputFormat::Latex => return,
+            OutputFormat::Csv if self.show_error => "expression,value,error,rel_error",
+            OutputFormat::Csv => "expression,value,error",
+        };
+        let header = match self.name {
+            Some(_) => format!("name,{}", header),
+            None => header.to_string(),
+        };
+        println!("{}", header);
+        if !self.structured_log {
+            info!("{}", header);
+        }
+    }
+
+    /// 输出一个匹配结果，若与已输出的数值重复则跳过
+    pub fn report(&self, tokens: &[Token], value: f64) {
+        if let Some(unique) = &self.unique
+            && !unique.insert(value)
+        {
+            return;
+        }
+        // 开启 --simplify 时输出化简后的等价表达式
+        let simplified = self
+            .simplify
+            .then(|| Expr::from_rpn(tokens).map(|expr| expr.simplify().to_rpn()))
+            .flatten();
+        let tokens = simplified.as_deref().unwrap_or(tokens);
+        let error = value - self.target;
+        let line = self.line(tokens, value);
+        println!("{}", line);
+        if self.structured_log {
+            match &self.name {
+                Some(name) => info!(
+                    name = %name,
+                    rpn = %TokenVec(tokens),
+                    value,
+                    target = self.target,
+                    error,
+                    "match"
+                ),
+                None => info!(
+                    rpn = %TokenVec(tokens),
+                    value,
+                    target = self.target,
+                    error,
+                    "match"
+                ),
+            }
+        } else {
+            info!("{}", line);
+        }
+    }
+
+    /// 按输出格式把一个匹配结果写成一行，设置了 `name` 时以目标名称标记
+    fn line(&self, tokens: &[Token], value: f64) -> String {
+        let error = value - self.target;
+        let rel_error = error / self.target; // 目标值为0时为无穷或 NaN
+        let digits = self.precision;
+        let mut line = match self.format {
+            OutputFormat::Human => format!("{}: {}", TokenVec(tokens), significant(value, digits)),
+            OutputFormat::Csv => format!(
+                "{},{},{}",
+                csv_quote(&TokenVec(tokens).to_string()),
+                significant(value, digits),
+                significant(error, digits)
+            ),
+            OutputFormat::Jsonl => format!(
+                r#"{{"rpn": {}, "infix": {}, "value": {}, "target": {}, "error": {}"#,
+                json_string(&TokenVec(tokens).to_string()),
+                json_string(&to_infix_minimal(tokens)),
+                significant(value, digits),
+                self.target,
+                significant(error, digits)
+            ),
+            OutputFormat::Latex => format!(
+                r"{} \approx {}",
+                to_latex(tokens),
+                significant(value, digits)
+            ),
+        };
+        if self.show_error {
+            let suffix = match self.format {
+                OutputFormat::Human => format!(
+                    "  (error = {:+.*e}, rel_error = {:+.3e})",
+                    digits - 1,
+                    error,
+                    rel_error
+                ),
+                OutputFormat::Csv => format!(",{}", significant(rel_error, digits)),
+                // JSON 不能表示无穷和 NaN
+                OutputFormat::Jsonl if rel_error.is_finite() => {
+                    format!(r#", "rel_error": {}"#, significant(rel_error, digits))
+                }
+                OutputFormat::Jsonl => r#", "rel_error": null"#.to_string(),
+                OutputFormat::Latex => format!(
+                    "  % error = {:+.*e}, rel_error = {:+.3e}",
+                    digits - 1,
+                    error,
+                    rel_error
+                ),
+            };
+            line.push_str(&suffix);
+        }
+        if self.format == OutputFormat::Jsonl {
+            line.push('}');
+        }
+        if let Some(name) = &self.name {
+            line = match self.format {
+                OutputFormat::Human => format!("[{}] {}", name, line),
+                OutputFormat::Csv => format!("{},{}", csv_quote(name), line),
+                OutputFormat::Jsonl => {
+                    format!(r#"{{"name": {}, {}"#, json_string(name), &line[1..])
+                }
+                OutputFormat::Latex => format!("{}  % {}", line, name),
+            };
+        }
+        line
+    }
+}
+
+/// 按 CSV 规则给字段加引号，字段内的引号需要重复一次
+fn csv_quote(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+/// 按 `digits` 位有效数字格式化数值
+///
+/// 指数在 [-5, digits) 之间时使用定点表示并保留末尾的 0，使同一次输出的位数一致，
+/// 其余情况使用科学计数法，两种形式都是合法的 JSON 数字。
+fn significant(value: f64, digits: usize) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let digits = digits.max(1);
+    // 先按科学计数法舍入，再取舍入后的指数，避免 9.99..95 进位后多出一位
+    let scientific = format!("{:.*e}", digits - 1, value);
+    let exponent: i32 = scientific[scientific.find('e').unwrap() + 1..]
+        .parse()
+        .unwrap();
+    if (-5..digits as i32).contains(&exponent) {
+        format!("{:.*}", (digits as i32 - 1 - exponent) as usize, value)
+    } else {
+        scientific
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tf_solver::parser::parse_rpn;
+
+    fn reporter(format: OutputFormat, name: Option<&str>) -> Reporter {
+        Reporter {
+            format,
+            target: 3.0,
+            unique: None,
+            show_error: false,
+            precision: 4,
+            simplify: false,
+            structured_log: false,
+            name: name.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_line_with_name() {
+        let tokens = parse_rpn("pi").unwrap();
+        let value = std::f64::consts::PI;
+        let line = |format, name| reporter(format, name).line(&tokens, value);
+        assert_eq!(line(OutputFormat::Human, None), "pi: 3.142");
+        assert_eq!(line(OutputFormat::Human, Some("g")), "[g] pi: 3.142");
+        assert_eq!(line(OutputFormat::Csv, None), r#""pi",3.142,0.1416"#);
+        // 名称中的逗号和引号按 CSV 规则转义
+        assert_eq!(
+            line(OutputFormat::Csv, Some(r#"a, "b""#)),
+            r#""a, ""b""","pi",3.142,0.1416"#
+        );
+        assert_eq!(
+            line(OutputFormat::Jsonl, None),
+            r#"{"rpn": "pi", "infix": "pi", "value": 3.142, "target": 3, "error": 0.1416}"#
+        );
+        assert_eq!(
+            line(OutputFormat::Jsonl, Some("\"g\"")),
+            r#"{"name": "\"g\"", "rpn": "pi", "infix": "pi", "value": 3.142, "target": 3, "error": 0.1416}"#
+        );
+        assert_eq!(
+            line(OutputFormat::Latex, Some("g")),
+            format!("{}  % g", line(OutputFormat::Latex, None))
+        );
+
+        // --show-error 的后缀位于名称之后
+        let mut with_error = reporter(OutputFormat::Jsonl, Some("g"));
+        with_error.show_error = true;
+        assert_eq!(
+            with_error.line(&tokens, value),
+            r#"{"name": "g", "rpn": "pi", "infix": "pi", "value": 3.142, "target": 3, "error": 0.1416, "rel_error": 0.04720}"#
+        );
+    }
+
+    #[test]
+    fn test_csv_quote() {
+        assert_eq!(csv_quote(""), r#""""#);
+        assert_eq!(csv_quote("pi e +"), r#""pi e +""#);
+        // 逗号和换行位于引号内，引号重复一次
+        assert_eq!(csv_quote("a,b"), r#""a,b""#);
+        assert_eq!(csv_quote("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_quote(r#"say "hi""#), r#""say ""hi""""#);
+        assert_eq!(csv_quote(r#"""#), r#""""""#);
+    }
+
+    #[test]
+    fn test_significant() {
+        assert_eq!(significant(std::f64::consts::PI, 10), "3.141592654");
+        assert_eq!(significant(20.0, 4), "20.00");
+        assert_eq!(significant(-0.0012345678, 3), "-0.00123");
+        assert_eq!(significant(9.9996, 4), "10.00"); // 舍入后进位
+        assert_eq!(significant(123456.0, 3), "1.23e5");
+        assert_eq!(significant(1.5e-7, 2), "1.5e-7");
+        assert_eq!(significant(0.0, 3), "0.00");
+        assert_eq!(significant(f64::NAN, 3), "NaN");
+    }
+}
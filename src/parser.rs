@@ -0,0 +1,380 @@
+// src/parser.rs
+use crate::opes_data::{CONSTANTS, prepare_opes};
+use crate::rpn::is_valid_rpn;
+use crate::types::{BinaryOperator, Operand, Token, UnaryOperator};
+
+/// 把以空格分隔的RPN字符串解析为token序列，如 `"pi sqrt e 2 ^ +"`
+///
+/// 运算符取自 `prepare_opes`，常数可以用常数表中的名称或符号书写，
+/// 其余能解析为有限数字的单词作为数值操作数，`nan`、`inf` 等非有限的字面量会被拒绝。
+pub fn parse_rpn(text: &str) -> Result<Vec<Token>, String> {
+    let (_, unary_operators, binary_operators) = prepare_opes();
+    let operands = constant_operands();
+    parse_rpn_with(text, &operands, &unary_operators, &binary_operators)
+}
+
+/// 常数表中的所有常数
+fn constant_operands() -> Vec<Operand> {
+    CONSTANTS
+        .iter()
+        .map(|constant| Operand {
+            symbol: constant.symbol.to_string(),
+            value: constant.value,
+        })
+        .collect()
+}
+
+/// 与 `parse_rpn` 相同，但使用给定的操作数和运算符表
+///
+/// 常数名称（如 `euler`）总会被识别为对应的符号，即使它不在 `operands` 中。
+pub fn parse_rpn_with(
+    text: &str,
+    operands: &[Operand],
+    unary_operators: &[UnaryOperator],
+    binary_operators: &[BinaryOperator],
+) -> Result<Vec<Token>, String> {
+    let tokens = text
+        .split_whitespace()
+        .map(|word| {
+            // 命令行中的常数名称，如 euler -> γ
+            let symbol = CONSTANTS
+                .iter()
+                .find(|constant| constant.name == word)
+                .map_or(word, |constant| constant.symbol);
+            if let Some(operand) = operands.iter().find(|operand| operand.symbol == symbol) {
+                Ok(Token::Operand(operand.clone()))
+            } else if let Some(operator) = unary_operators.iter().find(|o| o.symbol == symbol) {
+                Ok(Token::UnaryOperator(operator.clone()))
+            } else if let Some(operator) = binary_operators.iter().find(|o| o.symbol == symbol) {
+                Ok(Token::BinaryOperator(operator.clone()))
+            } else if let Ok(value) = word.parse::<f64>() {
+                if !value.is_finite() {
+                    return Err(format!("number must be finite: {}", word));
+                }
+                Ok(Token::Operand(Operand {
+                    symbol: word.to_string(),
+                    value,
+                }))
+            } else {
+                Err(format!("unknown symbol: {}", word))
+            }
+        })
+        .collect::<Result<Vec<Token>, String>>()?;
+
+    if !is_valid_rpn(&tokens) {
+        return Err(format!("invalid RPN expression: {}", text.trim()));
+    }
+    Ok(tokens)
+}
+
+/// 把中缀表达式解析为token序列，如 `"sqrt(pi) + e ^ 2"`
+///
+/// 支持 `+ - * / ^ mod` 及其优先级（`^` 右结合）、后缀 `!`、括号，
+/// 其余运算符写成函数调用，如 `sin(x)`、`min(a, b)`，可以解析 `to_infix` 和 `to_infix_minimal` 的输出。
+pub fn parse_infix(text: &str) -> Result<Vec<Token>, String> {
+    let (_, unary_operators, binary_operators) = prepare_opes();
+    let operands = constant_operands();
+    parse_infix_with(text, &operands, &unary_operators, &binary_operators)
+}
+
+/// 与 `parse_infix` 相同，但使用给定的操作数和运算符表
+pub fn parse_infix_with(
+    text: &str,
+    operands: &[Operand],
+    unary_operators: &[UnaryOperator],
+    binary_operators: &[BinaryOperator],
+) -> Result<Vec<Token>, String> {
+    let mut parser = InfixParser {
+        words: split_infix(text),
+        position: 0,
+        operands,
+        unary_operators,
+        binary_operators,
+        output: Vec::new(),
+    };
+    parser.expression()?;
+    if let Some(word) = parser.peek() {
+        return Err(format!("unexpected '{}'", word));
+    }
+    Ok(parser.output)
+}
+
+// 中缀表达式中单独成词的字符
+const INFIX_PUNCTUATION: &str = "+-*/^!(),";
+
+/// 把中缀表达式切分为单词：标点单独成词，其余连续字符组成符号或数字
+///
+/// 科学计数法中指数的符号属于数字本身，如 `1e-5` 是一个单词。
+fn split_infix(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if (c == '+' || c == '-') && is_mantissa(&current) {
+            current.push(c);
+        } else if c.is_whitespace() || INFIX_PUNCTUATION.contains(c) {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            if !c.is_whitespace() {
+                words.push(c.to_string());
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// `word` 是以 `e` 或 `E` 结尾的数字，如 `1e`、`2.5E`，其后的 `+`、`-` 是指数的符号
+fn is_mantissa(word: &str) -> bool {
+    word.strip_suffix(['e', 'E']).is_some_and(|mantissa| {
+        mantissa.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+            && mantissa.parse::<f64>().is_ok()
+    })
+}
+
+/// 递归下降解析器，边解析边按RPN顺序输出token
+struct InfixParser<'a> {
+    words: Vec<String>,
+    position: usize,
+    operands: &'a [Operand],
+    unary_operators: &'a [UnaryOperator],
+    binary_operators: &'a [BinaryOperator],
+    output: Vec<Token>,
+}
+
+impl InfixParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.words.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let word = self.words.get(self.position).cloned();
+        self.position += 1;
+        word.ok_or_else(|| "unexpected end of expression".to_string())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            word if word == expected => Ok(()),
+            word => Err(format!("expected '{}', found '{}'", expected, word)),
+        }
+    }
+
+    /// 输出符号对应的二元运算符
+    fn push_binary(&mut self, symbol: &str) -> Result<(), String> {
+        let operator = self
+            .binary_operators
+            .iter()
+            .find(|operator| operator.symbol == symbol)
+            .ok_or_else(|| format!("unknown operator: {}", symbol))?;
+        self.output.push(Token::BinaryOperator(operator.clone()));
+        Ok(())
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<(), String> {
+        self.term()?;
+        while let Some(symbol @ ("+" | "-")) = self.peek() {
+            let symbol = symbol.to_string();
+            self.position += 1;
+            self.term()?;
+            self.push_binary(&symbol)?;
+        }
+        Ok(())
+    }
+
+    // term := power (('*' | '/' | 'mod') power)*
+    fn term(&mut self) -> Result<(), String> {
+        self.power()?;
+        while let Some(symbol @ ("*" | "/" | "mod")) = self.peek() {
+            let symbol = symbol.to_string();
+            self.position += 1;
+            self.power()?;
+            self.push_binary(&symbol)?;
+        }
+        Ok(())
+    }
+
+    // power := postfix ('^' power)?，右结合
+    fn power(&mut self) -> Result<(), String> {
+        self.postfix()?;
+        if self.peek() == Some("^") {
+            self.position += 1;
+            self.power()?;
+            self.push_binary("^")?;
+        }
+        Ok(())
+    }
+
+    // postfix := primary '!'*
+    fn postfix(&mut self) -> Result<(), String> {
+        self.primary()?;
+        while self.peek() == Some("!") {
+            self.position += 1;
+            self.apply_function("!", 1)?;
+        }
+        Ok(())
+    }
+
+    // primary := '(' expression ')' | '-'? number | symbol | function '(' expression (',' expression)? ')'
+    fn primary(&mut self) -> Result<(), String> {
+        let word = self.next()?;
+        if word == "(" {
+            self.expression()?;
+            return self.expect(")");
+        }
+        if self.peek() == Some("(") {
+            self.position += 1;
+            self.expression()?;
+            let mut arguments = 1;
+            while self.peek() == Some(",") {
+                self.position += 1;
+                self.expression()?;
+                arguments += 1;
+            }
+            self.expect(")")?;
+            return self.apply_function(&word, arguments);
+        }
+        let (word, negative) = if word == "-" {
+            (self.next()?, true)
+        } else {
+            (word, false)
+        };
+        // 常数名称，如 euler -> γ
+        let symbol = CONSTANTS
+            .iter()
+            .find(|constant| constant.name == word)
+            .map_or(word.as_str(), |constant| constant.symbol);
+        if !negative
+            && let Some(operand) = self
+                .operands
+                .iter()
+                .find(|operand| operand.symbol == symbol)
+        {
+            self.output.push(Token::Operand(operand.clone()));
+            return Ok(());
+        }
+        match word.parse::<f64>() {
+            Ok(value) if !value.is_finite() => {
+                return Err(format!("number must be finite: {}", word));
+            }
+            Ok(value) if negative => self.output.push(Token::Operand(Operand {
+                symbol: format!("-{}", word),
+                value: -value,
+            })),
+            Ok(value) => self.output.push(Token::Operand(Operand {
+                symbol: word,
+                value,
+            })),
+            Err(_) if negative => {
+                return Err(format!("negation is only supported for numbers: -{}", word));
+            }
+            Err(_) => return Err(format!("unknown symbol: {}", word)),
+        }
+        Ok(())
+    }
+
+    /// 输出一个以函数形式书写的运算符，参数个数决定查找一元还是二元运算符表
+    fn apply_function(&mut self, symbol: &str, arguments: usize) -> Result<(), String> {
+        match arguments {
+            1 => {
+                let operator = self
+                    .unary_operators
+                    .iter()
+                    .find(|operator| operator.symbol == symbol)
+                    .ok_or_else(|| format!("unknown function: {}", symbol))?;
+                self.output.push(Token::UnaryOperator(operator.clone()));
+                Ok(())
+            }
+            2 => self.push_binary(symbol),
+            _ => Err(format!("{} takes at most 2 arguments", symbol)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpn::calculate;
+    use crate::types::TokenVec;
+
+    #[test]
+    fn test_parse_rpn() {
+        let tokens = parse_rpn("pi sqrt e 2 ^ +").unwrap();
+        assert_eq!(TokenVec(&tokens).to_string(), "pi sqrt e 2 ^ +");
+        let expected = std::f64::consts::PI.sqrt() + std::f64::consts::E.powi(2);
+        assert!((calculate(&tokens) - expected).abs() < 1e-12);
+
+        // 常数既可以写名称也可以写符号
+        let tokens = parse_rpn("  euler γ -  ").unwrap();
+        assert_eq!(TokenVec(&tokens).to_string(), "γ γ -");
+        assert_eq!(calculate(&tokens), 0.0);
+
+        assert_eq!(parse_rpn("1.5 -2 *").map(|t| calculate(&t)), Ok(-3.0));
+    }
+
+    #[test]
+    fn test_parse_rpn_errors() {
+        assert_eq!(
+            parse_rpn("pi foo +").err(),
+            Some("unknown symbol: foo".to_string())
+        );
+        assert_eq!(
+            parse_rpn("pi e").err(),
+            Some("invalid RPN expression: pi e".to_string())
+        );
+        assert!(parse_rpn("+").is_err());
+        assert!(parse_rpn("").is_err());
+        // 非有限的数值字面量
+        for word in ["nan", "NaN", "inf", "-inf", "infinity", "1e999"] {
+            assert_eq!(
+                parse_rpn(&format!("pi {} +", word)).err(),
+                Some(format!("number must be finite: {}", word))
+            );
+        }
+        assert_eq!(
+            parse_infix("pi + inf").err(),
+            Some("number must be finite: inf".to_string())
+        );
+        assert!(parse_infix("-nan").is_err());
+        assert!(parse_infix("1e999 * 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_infix() {
+        let rpn = |text: &str| parse_infix(text).map(|tokens| TokenVec(&tokens).to_string());
+        assert_eq!(rpn("sqrt(pi) + e ^ 2"), Ok("pi sqrt e 2 ^ +".to_string()));
+        assert_eq!(rpn("e - pi - γ"), Ok("e pi - γ -".to_string()));
+        assert_eq!(rpn("e ^ pi ^ 2"), Ok("e pi 2 ^ ^".to_string()));
+        assert_eq!(
+            rpn("e * (pi + 1) mod 3"),
+            Ok("e pi 1 + * 3 mod".to_string())
+        );
+        assert_eq!(rpn("min(e, -2) / (pi)!"), Ok("e -2 min pi ! /".to_string()));
+        assert_eq!(rpn("euler"), Ok("γ".to_string()));
+        // 指数的符号属于数字，常数 e 后的符号仍是运算符
+        assert_eq!(rpn("1e-5 + pi"), Ok("1e-5 pi +".to_string()));
+        assert_eq!(rpn("2.5E+3*-1e-2"), Ok("2.5E+3 -1e-2 *".to_string()));
+        assert_eq!(rpn("e-1"), Ok("e 1 -".to_string()));
+        assert_eq!(rpn("pi*e-e"), Ok("pi e * e -".to_string()));
+        assert_eq!(
+            parse_infix("1e-5 + pi").map(|tokens| calculate(&tokens)),
+            Ok(1e-5 + std::f64::consts::PI)
+        );
+        assert_eq!(rpn("pi +"), Err("unexpected end of expression".to_string()));
+        assert_eq!(rpn("(pi"), Err("unexpected end of expression".to_string()));
+        assert_eq!(rpn("pi pi"), Err("unexpected 'pi'".to_string()));
+        assert_eq!(rpn("foo(pi)"), Err("unknown function: foo".to_string()));
+
+        // to_infix 的输出可以解析回原来的表达式
+        let tokens = parse_rpn("pi e + ! γ sqrt e logb atan2").unwrap();
+        assert_eq!(
+            rpn(&crate::infix::to_infix(&tokens)),
+            Ok(TokenVec(&tokens).to_string())
+        );
+    }
+}
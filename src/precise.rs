@@ -0,0 +1,36 @@
+// src/precise.rs
+use tf_solver::opes_data;
+use tf_solver::rpn::calculate_as;
+use tf_solver::types::{BinaryOperatorOf, Token, UnaryOperatorOf};
+
+/// f64 粗筛阶段允许的相对误差，足以容纳 f64 的舍入误差
+const PREFILTER_RELATIVE_TOLERANCE: f64 = 1e-9;
+
+/// 用 f128 复核 f64 阶段找到的候选表达式
+///
+/// f64 的舍入误差会让恒等式看起来只是“接近”，因此 f64 阶段只按较宽的容差粗筛，
+/// 真正的容差判断交给 f128 的计算结果。
+pub struct Verifier {
+    unary_operators: Vec<UnaryOperatorOf<f128>>,
+    binary_operators: Vec<BinaryOperatorOf<f128>>,
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        let (unary_operators, binary_operators) = opes_data::operators::<f128>();
+        Self {
+            unary_operators,
+            binary_operators,
+        }
+    }
+
+    /// 若表达式在 f128 下与目标的差小于 `tolerance`，返回其 f128 值（舍入为 f64）
+    pub fn verify(&self, tokens: &[Token], value: f64, target: f64, tolerance: f64) -> Option<f64> {
+        let prefilter = tolerance.max(PREFILTER_RELATIVE_TOLERANCE * target.abs().max(1.0));
+        if (value - target).abs() >= prefilter {
+            return None;
+        }
+        let precise = calculate_as(tokens, &self.unary_operators, &self.binary_operators)?;
+        ((precise - target as f128).abs() < tolerance as f128).then_some(precise as f64)
+    }
+}
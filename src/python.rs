@@ -0,0 +1,49 @@
+// src/python.rs
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::parser::parse_rpn;
+use crate::rank::Complexity;
+use crate::rpn::calculate_checked;
+use crate::types::TokenVec;
+
+/// 搜索与 `target` 相差小于 `tolerance` 的表达式，返回 `(rpn, value)` 列表
+///
+/// 结果按复杂度从简到繁排序（与 --sort-simple 相同），`top` 限制返回的个数。
+/// 搜索期间释放 GIL，其他 Python 线程可以继续运行。
+#[pyfunction]
+#[pyo3(signature = (target, max_depth = 6, tolerance = 1.0, top = None))]
+fn solve(
+    py: Python<'_>,
+    target: f64,
+    max_depth: usize,
+    tolerance: f64,
+    top: Option<usize>,
+) -> Vec<(String, f64)> {
+    py.detach(|| {
+        let mut matches = crate::solve::solve(target, max_depth, tolerance);
+        matches.sort_by_cached_key(|m| Complexity::new(&m.tokens, m.value, target));
+        matches.truncate(top.unwrap_or(usize::MAX));
+        matches
+            .into_iter()
+            .map(|m| (TokenVec(&m.tokens).to_string(), m.value))
+            .collect()
+    })
+}
+
+/// 计算以空格分隔的RPN字符串的值，如 `calculate("pi sqrt e 2 ^ +")`
+///
+/// 无法解析、超出定义域或结果不是有限值时抛出 `ValueError`。
+#[pyfunction]
+fn calculate(rpn: &str) -> PyResult<f64> {
+    let tokens = parse_rpn(rpn).map_err(PyValueError::new_err)?;
+    calculate_checked(&tokens)
+        .ok_or_else(|| PyValueError::new_err(format!("expression has no finite value: {}", rpn)))
+}
+
+#[pymodule]
+fn tf_solver(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(solve, module)?)?;
+    module.add_function(wrap_pyfunction!(calculate, module)?)?;
+    Ok(())
+}
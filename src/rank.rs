@@ -0,0 +1,182 @@
+// src/rank.rs
+use crate::opes_data::CONSTANTS;
+use crate::types::{BinaryOperator, Operand, Token, UnaryOperator};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// 表达式的复杂度评分，用于把最简洁的匹配结果排在前面
+///
+/// 依次比较：token数量、不同运算符的数量、与目标值的绝对误差
+#[derive(Debug, Clone, Copy)]
+pub struct Complexity {
+    pub token_count: usize,        // token总数
+    pub distinct_operators: usize, // 不同运算符的个数
+    pub abs_error: f64,            // 与目标值的绝对误差
+}
+
+impl Complexity {
+    pub fn new(tokens: &[Token], value: f64, target: f64) -> Self {
+        let distinct_operators = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Operand(_) => None,
+                Token::UnaryOperator(operator) => Some(operator.symbol.as_str()),
+                Token::BinaryOperator(operator) => Some(operator.symbol.as_str()),
+            })
+            .collect::<HashSet<_>>()
+            .len();
+        Self {
+            token_count: tokens.len(),
+            distinct_operators,
+            abs_error: (value - target).abs(),
+        }
+    }
+}
+
+impl Ord for Complexity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.token_count
+            .cmp(&other.token_count)
+            .then(self.distinct_operators.cmp(&other.distinct_operators))
+            .then(self.abs_error.total_cmp(&other.abs_error))
+    }
+}
+
+impl PartialOrd for Complexity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Complexity {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Complexity {}
+
+/// 表达式是否用到了 `symbols` 中的每一个符号（操作数或运算符），用于 --must-use
+pub fn uses_all_symbols<S: AsRef<str>>(tokens: &[Token], symbols: &[S]) -> bool {
+    symbols
+        .iter()
+        .all(|symbol| tokens.iter().any(|token| token.symbol() == symbol.as_ref()))
+}
+
+/// 把 --must-use 的参数解析为表达式中的符号
+///
+/// 常数可以写名称（如 `euler` -> `γ`），与 --constants 相同；
+/// 解析后的符号必须是 `operands` 中的操作数或某个运算符，否则返回错误。
+pub fn resolve_symbols<S: AsRef<str>>(
+    names: &[S],
+    operands: &[Operand],
+    unary_operators: &[UnaryOperator],
+    binary_operators: &[BinaryOperator],
+) -> Result<Vec<String>, String> {
+    names
+        .iter()
+        .map(|name| {
+            let name = name.as_ref();
+            let symbol = CONSTANTS
+                .iter()
+                .find(|constant| constant.name == name)
+                .map_or(name, |constant| constant.symbol);
+            let known = operands.iter().any(|operand| operand.symbol == symbol)
+                || unary_operators
+                    .iter()
+                    .any(|operator| operator.symbol == symbol)
+                || binary_operators
+                    .iter()
+                    .any(|operator| operator.symbol == symbol);
+            if known {
+                Ok(symbol.to_string())
+            } else {
+                Err(format!(
+                    "--must-use {}: not an active operand or operator",
+                    name
+                ))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_rpn;
+
+    #[test]
+    fn test_complexity_ordering() {
+        let complexity =
+            |rpn: &str, value: f64| Complexity::new(&parse_rpn(rpn).unwrap(), value, 3.0);
+        let mut matches = [
+            ("pi sqrt sqrt e +", 3.0),
+            ("pi e sqrt +", 3.5),
+            ("pi", 3.2),
+            ("pi sin e +", 3.01),
+            ("pi e + sqrt", 2.9),
+            ("pi abs", 3.1),
+            ("pi e + e +", 3.9),
+        ];
+        // 先比较token数量，再比较不同运算符的个数，最后比较误差
+        matches.sort_by_key(|&(rpn, value)| complexity(rpn, value));
+        assert_eq!(
+            matches.iter().map(|(rpn, _)| *rpn).collect::<Vec<_>>(),
+            vec![
+                "pi",
+                "pi abs",
+                "pi sin e +",
+                "pi e + sqrt",
+                "pi e sqrt +",
+                "pi e + e +",
+                "pi sqrt sqrt e +",
+            ]
+        );
+        // 重复的运算符只计一次
+        let repeated = complexity("pi sqrt sqrt e +", 3.0);
+        assert_eq!(repeated.token_count, 5);
+        assert_eq!(repeated.distinct_operators, 2);
+        assert_eq!(repeated, complexity("pi sqrt sqrt e +", 3.0));
+        assert!(complexity("pi e +", 2.9) < complexity("pi e +", 3.2));
+    }
+
+    #[test]
+    fn test_resolve_symbols() {
+        let (_, unary_operators, binary_operators) = crate::opes_data::prepare_opes();
+        let operands = crate::opes_data::select_constants(&["pi", "euler", "sqrt2"]).unwrap();
+        let resolve =
+            |names: &[&str]| resolve_symbols(names, &operands, &unary_operators, &binary_operators);
+        // 常数的名称换成符号，符号和运算符保持不变
+        assert_eq!(
+            resolve(&["euler", "sqrt2", "pi", "γ", "sqrt", "+"]),
+            Ok(vec!["γ", "√2", "pi", "γ", "sqrt", "+"]
+                .into_iter()
+                .map(str::to_string)
+                .collect())
+        );
+        // 未启用的常数和未知的符号
+        assert!(resolve(&["catalan"]).is_err());
+        assert!(resolve(&["e"]).is_err());
+        assert!(resolve(&["foo"]).is_err());
+
+        let tokens = parse_rpn("pi sqrt γ +").unwrap();
+        assert!(uses_all_symbols(
+            &tokens,
+            &resolve(&["euler", "sqrt"]).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_uses_all_symbols() {
+        let tokens = parse_rpn("pi sqrt e +").unwrap();
+        assert!(uses_all_symbols::<&str>(&tokens, &[]));
+        assert!(uses_all_symbols(&tokens, &["pi"]));
+        assert!(uses_all_symbols(&tokens, &["e", "sqrt", "+", "pi"]));
+        // 缺少任意一个符号
+        assert!(!uses_all_symbols(&tokens, &["pi", "γ"]));
+        assert!(!uses_all_symbols(&tokens, &["sin"]));
+        // 按完整的符号比较，不匹配子串
+        assert!(!uses_all_symbols(&tokens, &["sq"]));
+        assert!(uses_all_symbols(&tokens, &["pi".to_string()]));
+    }
+}
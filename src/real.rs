@@ -0,0 +1,175 @@
+// src/real.rs
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+/// 运算符表和RPN计算所需的浮点数类型
+///
+/// 默认使用 `f64`；开启 `precision` 特性后还为 `f128` 实现，用于以更高精度复核匹配结果。
+pub trait Real:
+    Copy
+    + PartialOrd
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + Neg<Output = Self>
+    + Send
+    + Sync
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    /// 按符号返回常数在该类型精度下的值，`None` 表示沿用操作数的 `f64` 值
+    fn constant(_symbol: &str) -> Option<Self> {
+        None
+    }
+
+    fn is_finite(self) -> bool;
+    fn abs(self) -> Self;
+    fn fract(self) -> Self;
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn ln(self) -> Self;
+    fn log10(self) -> Self;
+    fn gamma(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn asin(self) -> Self;
+    fn acos(self) -> Self;
+    fn atan(self) -> Self;
+    fn sinh(self) -> Self;
+    fn cosh(self) -> Self;
+    fn tanh(self) -> Self;
+    fn asinh(self) -> Self;
+    fn acosh(self) -> Self;
+    fn atanh(self) -> Self;
+    fn powf(self, exponent: Self) -> Self;
+    fn log(self, base: Self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+}
+
+// 所有方法都直接转发给浮点类型自身的同名方法
+macro_rules! impl_real {
+    ($t:ident { $($extra:item)* }) => {
+        impl Real for $t {
+            const ZERO: Self = 0.0;
+            const ONE: Self = 1.0;
+
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            $($extra)*
+
+            impl_real!(@unary $t: is_finite -> bool);
+            impl_real!(@unary $t: abs, fract, floor, ceil, sqrt, ln, log10);
+            impl_real!(@unary $t: sin, cos, tan, asin, acos, atan);
+            impl_real!(@unary $t: sinh, cosh, tanh, asinh, acosh, atanh);
+            impl_real!(@binary $t: powf, log, atan2, min, max);
+        }
+    };
+    (@unary $t:ident: $method:ident -> $ret:ty) => {
+        fn $method(self) -> $ret {
+            $t::$method(self)
+        }
+    };
+    (@unary $t:ident: $($method:ident),*) => {
+        $(
+            fn $method(self) -> Self {
+                $t::$method(self)
+            }
+        )*
+    };
+    (@binary $t:ident: $($method:ident),*) => {
+        $(
+            fn $method(self, other: Self) -> Self {
+                $t::$method(self, other)
+            }
+        )*
+    };
+}
+
+impl_real!(f64 {
+    fn gamma(self) -> Self {
+        gamma(self)
+    }
+});
+
+#[cfg(feature = "precision")]
+impl_real!(f128 {
+    fn gamma(self) -> Self {
+        f128::gamma(self)
+    }
+
+    #[allow(clippy::excessive_precision)]
+    fn constant(symbol: &str) -> Option<Self> {
+        use std::f128::consts;
+        // 与 opes_data::CONSTANTS 中的符号一一对应
+        match symbol {
+            "e" => Some(consts::E),
+            "pi" => Some(consts::PI),
+            "γ" => Some(0.577215664901532860606512090082402431),
+            "φ" => Some(1.618033988749894848204586834365638118),
+            "√2" => Some(consts::SQRT_2),
+            "ln2" => Some(consts::LN_2),
+            "G" => Some(0.915965594177219015054603514932384110),
+            _ => None,
+        }
+    }
+});
+
+/// gamma 函数，不依赖 nightly 的 `float_gamma` 特性
+///
+/// 使用 `libm::tgamma`（纯 Rust 实现，同样可以编译到 wasm32），在非正整数（极点）处返回 `NaN`。
+pub fn gamma(x: f64) -> f64 {
+    if x <= 0.0 && x.fract() == 0.0 {
+        return f64::NAN; // 极点
+    }
+    libm::tgamma(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamma() {
+        use crate::ulp::within_ulps;
+
+        let sqrt_pi = std::f64::consts::PI.sqrt();
+        // 正整数处精确等于阶乘
+        let mut factorial = 1.0;
+        for n in 1..=23 {
+            assert_eq!(gamma(n as f64), factorial, "gamma({})", n);
+            factorial *= n as f64;
+        }
+        assert_eq!(gamma(5.0), 24.0);
+        assert!(within_ulps(gamma(0.5), sqrt_pi, 1));
+        // 其余参考值为真实值正确舍入到 f64 的结果
+        assert!(within_ulps(gamma(1.5), 0.886226925452758, 2));
+        assert!(within_ulps(gamma(-0.5), -3.544907701811032, 1));
+        assert!(within_ulps(
+            gamma(std::f64::consts::PI),
+            2.288037795340032,
+            1
+        ));
+        assert!(within_ulps(gamma(50.5), 4.29046291235196e63, 1));
+        assert!(gamma(0.0).is_nan());
+        assert!(gamma(-3.0).is_nan());
+        assert_eq!(gamma(200.0), f64::INFINITY);
+    }
+}
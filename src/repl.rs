@@ -0,0 +1,115 @@
+// src/repl.rs
+use std::io::{BufRead, IsTerminal, Write};
+
+use tf_solver::parser::{parse_infix_with, parse_rpn_with};
+use tf_solver::rpn::{calculate_checked, calculate_traced};
+use tf_solver::types::{BinaryOperator, Operand, Token, UnaryOperator};
+
+/// 逐行读取标准输入中的表达式并输出其值
+///
+/// 每行先按RPN解析，失败时再按中缀表达式解析。输入 `vars` 列出当前的操作数和运算符，
+/// `quit`/`exit` 或输入结束时退出。
+pub fn run(
+    operands: &[Operand],
+    unary_operators: &[UnaryOperator],
+    binary_operators: &[BinaryOperator],
+) {
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal(); // 只在交互式终端中显示提示符
+    let prompt = || {
+        if interactive {
+            eprint!("> ");
+            std::io::stderr().flush().ok();
+        }
+    };
+
+    prompt();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        match line.trim() {
+            "" => {}
+            "quit" | "exit" => break,
+            "vars" => print_vars(operands, unary_operators, binary_operators),
+            text => match parse(text, operands, unary_operators, binary_operators) {
+                Ok(tokens) => match calculate_checked(&tokens) {
+                    Some(value) => println!("{}", value),
+                    None => println!("error: expression has no finite value"),
+                },
+                Err(err) => println!("error: {}", err),
+            },
+        }
+        prompt();
+    }
+}
+
+/// 输出单个表达式的逐步计算过程，每行为处理的token以及之后的栈
+pub fn trace(
+    text: &str,
+    operands: &[Operand],
+    unary_operators: &[UnaryOperator],
+    binary_operators: &[BinaryOperator],
+) -> Result<(), String> {
+    let tokens = parse(text, operands, unary_operators, binary_operators)?;
+    let (value, steps) = calculate_traced(&tokens);
+    let width = steps
+        .iter()
+        .map(|step| step.symbol.chars().count())
+        .max()
+        .unwrap_or(0);
+    for step in &steps {
+        let stack: Vec<String> = step.stack.iter().map(|value| value.to_string()).collect();
+        println!(
+            "{:<width$}  [{}]",
+            step.symbol,
+            stack.join(", "),
+            width = width
+        );
+    }
+    if steps.len() < tokens.len() {
+        // calculate_traced 在超出定义域的那一步中止
+        return Err(format!(
+            "{} is applied outside its domain",
+            tokens[steps.len()].symbol()
+        ));
+    }
+    println!("= {}", value);
+    Ok(())
+}
+
+/// 先按RPN解析；失败时按中缀解析，含括号的输入报告中缀解析的错误
+fn parse(
+    text: &str,
+    operands: &[Operand],
+    unary_operators: &[UnaryOperator],
+    binary_operators: &[BinaryOperator],
+) -> Result<Vec<Token>, String> {
+    parse_rpn_with(text, operands, unary_operators, binary_operators).or_else(|rpn_err| {
+        parse_infix_with(text, operands, unary_operators, binary_operators).map_err(|infix_err| {
+            if text.contains('(') {
+                infix_err
+            } else {
+                rpn_err
+            }
+        })
+    })
+}
+
+/// 输出当前启用的操作数、一元运算符和二元运算符
+fn print_vars(
+    operands: &[Operand],
+    unary_operators: &[UnaryOperator],
+    binary_operators: &[BinaryOperator],
+) {
+    for operand in operands {
+        println!("{} = {}", operand.symbol, operand.value);
+    }
+    let symbols = |symbols: Vec<&str>| symbols.join(" ");
+    println!(
+        "unary: {}",
+        symbols(unary_operators.iter().map(|o| o.symbol.as_str()).collect())
+    );
+    println!(
+        "binary: {}",
+        symbols(binary_operators.iter().map(|o| o.symbol.as_str()).collect())
+    );
+}
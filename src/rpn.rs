@@ -1,5 +1,8 @@
 // src/rpn.rs
-use crate::types::{BinaryOperator, Token, UnaryOperator};
+use crate::real::Real;
+use crate::types::{
+    BinaryOperator, BinaryOperatorOf, Operand, Operator, Token, UnaryOperator, UnaryOperatorOf,
+};
 
 /// 检查RPN（逆波兰表达式）是否有效
 pub fn is_valid_rpn(tokens: &[Token]) -> bool {
@@ -39,27 +42,270 @@ pub fn is_valid_rpn(tokens: &[Token]) -> bool {
 ///
 /// 如果输入的 `tokens` 不是一个有效的 RPN 序列，该函数会 panic。
 /// 调用者应该首先使用 `is_valid_rpn` 来验证输入。
+///
+/// 若某个运算符被应用在其定义域之外，计算立即中止并返回 `NaN`。
 pub fn calculate(tokens: &[Token]) -> f64 {
+    calculate_with_limit(tokens, f64::INFINITY)
+}
+
+/// 计算RPN表达式的值，并限制中间结果的大小
+///
+/// 每次应用运算符后都会检查结果，一旦其绝对值超过 `max_magnitude`，
+/// 计算立即中止并返回 `NaN`，用于尽早丢弃 `^`、`gamma` 等爆炸性增长的分支。
+///
+/// # Panics
+///
+/// 与 `calculate` 相同，输入不是有效的 RPN 序列时会 panic。
+pub fn calculate_with_limit(tokens: &[Token], max_magnitude: f64) -> f64 {
     // 虽然主逻辑会检查，但这里加断言更明确
     assert!(is_valid_rpn(tokens), "Invalid RPN sequence passed to calculate");
 
+    evaluate(tokens, max_magnitude).unwrap_or(f64::NAN)
+}
+
+/// 计算RPN表达式的值，任何情况下都不会 panic
+///
+/// 输入不是有效的 RPN 序列、运算符超出定义域或结果为 `NaN`/无穷时返回 `None`，
+/// 适合用来计算来自解析器或配置文件等不可信来源的表达式。
+pub fn calculate_checked(tokens: &[Token]) -> Option<f64> {
+    evaluate(tokens, f64::INFINITY).filter(|value| value.is_finite())
+}
+
+/// 计算过程中的一步：处理一个token之后的栈状态
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStep {
+    pub symbol: String,  // 本步处理的token（操作数或运算符）
+    pub stack: Vec<f64>, // 处理之后栈中的值，栈顶在最后
+}
+
+/// 计算RPN表达式的值，并记录每一步之后的栈状态
+///
+/// 返回值与 `calculate` 相同；若运算符超出定义域，计算在该步中止，
+/// 返回 `NaN` 以及此前成功的各步。
+///
+/// # Panics
+///
+/// 与 `calculate` 相同，输入不是有效的 RPN 序列时会 panic。
+pub fn calculate_traced(tokens: &[Token]) -> (f64, Vec<TraceStep>) {
+    assert!(
+        is_valid_rpn(tokens),
+        "Invalid RPN sequence passed to calculate_traced"
+    );
+
+    let mut steps = Vec::new();
+    let value = evaluate_with(
+        tokens,
+        f64::INFINITY,
+        |operand| operand.value,
+        Some,
+        Some,
+        |token, stack| {
+            steps.push(TraceStep {
+                symbol: token.symbol().to_string(),
+                stack: stack.to_vec(),
+            })
+        },
+    );
+    (value.unwrap_or(f64::NAN), steps)
+}
+
+/// 以数值类型 `R` 重新计算RPN表达式，任何情况下都不会 panic
+///
+/// 运算符按符号在 `R` 的运算符表中查找同名项（通常来自 `opes_data::operators::<R>()`），
+/// 常数操作数取 `Real::constant` 给出的值，其余操作数从 `f64` 转换。
+/// 序列无效、符号不在表中、运算符超出定义域或结果不是有限值时返回 `None`。
+pub fn calculate_as<R: Real>(
+    tokens: &[Token],
+    unary_operators: &[UnaryOperatorOf<R>],
+    binary_operators: &[BinaryOperatorOf<R>],
+) -> Option<R> {
+    evaluate_with(
+        tokens,
+        R::from_f64(f64::INFINITY),
+        |operand| R::constant(&operand.symbol).unwrap_or_else(|| R::from_f64(operand.value)),
+        |operator| {
+            unary_operators
+                .iter()
+                .find(|candidate| candidate.symbol == operator.symbol)
+        },
+        |operator| {
+            binary_operators
+                .iter()
+                .find(|candidate| candidate.symbol == operator.symbol)
+        },
+        |_, _| {},
+    )
+    .filter(|value| value.is_finite())
+}
+
+/// 用栈计算RPN表达式，栈中元素不足、运算符超出定义域或中间结果过大时返回 `None`
+fn evaluate(tokens: &[Token], max_magnitude: f64) -> Option<f64> {
+    evaluate_with(
+        tokens,
+        max_magnitude,
+        |operand| operand.value,
+        Some,
+        Some,
+        |_, _| {},
+    )
+}
+
+/// `evaluate` 的通用版本，由调用者决定操作数的取值以及每个运算符对应的 `R` 版本
+///
+/// 找不到运算符的 `R` 版本时同样返回 `None`。每处理完一个token调用一次 `on_step`。
+fn evaluate_with<'a, 'b, R: Real>(
+    tokens: &'a [Token],
+    max_magnitude: R,
+    operand_value: impl Fn(&Operand) -> R,
+    unary_operator: impl Fn(&'a UnaryOperator) -> Option<&'b UnaryOperatorOf<R>>,
+    binary_operator: impl Fn(&'a BinaryOperator) -> Option<&'b BinaryOperatorOf<R>>,
+    mut on_step: impl FnMut(&Token, &[R]),
+) -> Option<R> {
     let mut stack = Vec::new(); // 使用向量模拟栈
     for token in tokens {
         match token {
-            Token::Operand(operand) => stack.push(operand.value), // 操作数直接入栈
-            Token::UnaryOperator(UnaryOperator { function, .. }) => {
-                // is_valid_rpn 保证了此时栈不为空
-                let value = stack.pop().unwrap(); // 弹出操作数
-                stack.push(function(value)); // 应用一元运算符并压入结果
+            Token::Operand(operand) => stack.push(operand_value(operand)), // 操作数直接入栈
+            Token::UnaryOperator(operator) => {
+                let Operator {
+                    function, domain, ..
+                } = unary_operator(operator)?;
+                let value = stack.pop()?; // 弹出操作数
+                if domain.is_some_and(|in_domain| !in_domain(value)) {
+                    return None; // 超出定义域，丢弃该表达式
+                }
+                let result = function(value); // 应用一元运算符
+                if result.abs() > max_magnitude {
+                    return None; // 中间结果过大，丢弃该表达式
+                }
+                stack.push(result); // 压入结果
             }
-            Token::BinaryOperator(BinaryOperator { function, .. }) => {
-                 // is_valid_rpn 保证了此时栈至少有两个元素
-                let right = stack.pop().unwrap(); // 弹出右操作数
-                let left = stack.pop().unwrap(); // 弹出左操作数
-                stack.push(function(left, right)); // 应用二元运算符并压入结果
+            Token::BinaryOperator(operator) => {
+                let Operator {
+                    function, domain, ..
+                } = binary_operator(operator)?;
+                let right = stack.pop()?; // 弹出右操作数
+                let left = stack.pop()?; // 弹出左操作数
+                if domain.is_some_and(|in_domain| !in_domain(left, right)) {
+                    return None; // 超出定义域，丢弃该表达式
+                }
+                let result = function(left, right); // 应用二元运算符
+                if result.abs() > max_magnitude {
+                    return None; // 中间结果过大，丢弃该表达式
+                }
+                stack.push(result); // 压入结果
             }
         } // End match
+        on_step(token, &stack);
+    }
+    // 有效的RPN序列计算完成后栈中只剩一个元素
+    match stack[..] {
+        [result] => Some(result),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opes_data;
+    use crate::parser::parse_rpn;
+
+    #[test]
+    fn test_calculate_checked() {
+        let two = Token::Operand(Operand {
+            symbol: "2".to_string(),
+            value: 2.0,
+        });
+        let zero = Token::Operand(Operand {
+            symbol: "0".to_string(),
+            value: 0.0,
+        });
+        let divide = Token::BinaryOperator(BinaryOperator::new("/".to_string(), |a, b| a / b));
+
+        assert_eq!(calculate_checked(std::slice::from_ref(&two)), Some(2.0));
+        assert_eq!(calculate_checked(&[]), None); // 空序列
+        assert_eq!(calculate_checked(&[two.clone(), divide.clone()]), None); // 栈中元素不足
+        assert_eq!(calculate_checked(&[two.clone(), two.clone()]), None); // 栈中剩余多个元素
+        assert_eq!(calculate_checked(&[two, zero, divide]), None); // 结果为无穷
+    }
+
+    #[test]
+    fn test_calculate_traced() {
+        let step = |symbol: &str, stack: &[f64]| TraceStep {
+            symbol: symbol.to_string(),
+            stack: stack.to_vec(),
+        };
+        let tokens = parse_rpn("2 3 + 4 * sqrt").unwrap();
+        let (value, steps) = calculate_traced(&tokens);
+        assert_eq!(value, calculate(&tokens));
+        assert_eq!(
+            steps,
+            vec![
+                step("2", &[2.0]),
+                step("3", &[2.0, 3.0]),
+                step("+", &[5.0]),
+                step("4", &[5.0, 4.0]),
+                step("*", &[20.0]),
+                step("sqrt", &[20.0_f64.sqrt()]),
+            ]
+        );
+
+        // 超出定义域时在该步中止
+        let (value, steps) = calculate_traced(&parse_rpn("2 asin 1 +").unwrap());
+        assert!(value.is_nan());
+        assert_eq!(steps, vec![step("2", &[2.0])]);
+    }
+
+    #[test]
+    fn test_calculate_as() {
+        let (operands, unary_operators, binary_operators) = opes_data::prepare_opes();
+        let pi = || Token::Operand(operands[1].clone());
+        let operator = |symbol: &str| {
+            unary_operators
+                .iter()
+                .find(|operator| operator.symbol == symbol)
+                .map(|operator| Token::UnaryOperator(operator.clone()))
+                .or_else(|| {
+                    binary_operators
+                        .iter()
+                        .find(|operator| operator.symbol == symbol)
+                        .map(|operator| Token::BinaryOperator(operator.clone()))
+                })
+                .unwrap()
+        };
+        // sin(pi) 在 f64 下只是接近0
+        let tokens = vec![pi(), operator("sin")];
+        let (unary, binary) = opes_data::operators::<f64>();
+        assert_eq!(
+            calculate_as(&tokens, &unary, &binary),
+            Some(calculate(&tokens))
+        );
+        assert!(calculate(&tokens) > 1e-17);
+
+        #[cfg(feature = "precision")]
+        {
+            let (unary, binary) = opes_data::operators::<f128>();
+            let value = calculate_as(&tokens, &unary, &binary).unwrap();
+            assert!((value as f64).abs() < 1e-30);
+            // sqrt(pi)^2 与 pi 在 f128 下的差远小于 f64 的精度
+            let two = Token::Operand(Operand {
+                symbol: "2".to_string(),
+                value: 2.0,
+            });
+            let tokens = vec![
+                pi(),
+                operator("sqrt"),
+                two,
+                operator("^"),
+                pi(),
+                operator("-"),
+            ];
+            let value = calculate_as(&tokens, &unary, &binary).unwrap();
+            assert!((value as f64).abs() < 1e-30);
+        }
+
+        // 表中没有的运算符
+        let square = Token::UnaryOperator(UnaryOperator::new("^2".to_string(), |a| a * a));
+        assert_eq!(calculate_as(&[pi(), square], &unary, &binary), None);
     }
-    // is_valid_rpn 保证了最终栈中只有一个元素
-    stack.pop().unwrap() // 返回最终结果
 }
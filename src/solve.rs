@@ -0,0 +1,76 @@
+// src/solve.rs
+use crate::generator::{GenConfig, generate_valid_tokens};
+use crate::infix::to_infix_minimal;
+use crate::json::json_string;
+use crate::opes_data::prepare_opes;
+use crate::rpn::calculate_with_limit;
+use crate::types::{Token, TokenVec};
+
+/// 中间结果绝对值的上限，与命令行 --max-magnitude 的默认值相同
+const MAX_MAGNITUDE: f64 = 1e12;
+
+/// 一个匹配结果
+pub struct Match {
+    pub tokens: Vec<Token>, // 表达式的token序列
+    pub value: f64,         // 表达式的值
+}
+
+impl Match {
+    /// 编码为 JSON 对象：`{"rpn", "infix", "value", "error"}`
+    pub fn to_json(&self, target: f64) -> String {
+        format!(
+            r#"{{"rpn": {}, "infix": {}, "value": {}, "error": {}}}"#,
+            json_string(&TokenVec(&self.tokens).to_string()),
+            json_string(&to_infix_minimal(&self.tokens)),
+            self.value,
+            self.value - target
+        )
+    }
+}
+
+/// 在当前线程中搜索与 `target` 相差小于 `tolerance` 的表达式
+///
+/// 使用默认的常数和运算符，枚举深度不超过 `max_depth` 的所有表达式。
+/// 不依赖线程池和通道，适合 wasm 等单线程环境；命令行程序使用的是并行版本。
+pub fn solve(target: f64, max_depth: usize, tolerance: f64) -> Vec<Match> {
+    let (operands, unary_operators, binary_operators) = prepare_opes();
+    generate_valid_tokens(
+        &operands,
+        &unary_operators,
+        &binary_operators,
+        max_depth,
+        GenConfig::default(),
+    )
+    .filter_map(|tokens| {
+        let value = calculate_with_limit(&tokens, MAX_MAGNITUDE);
+        ((value - target).abs() < tolerance).then_some(Match { tokens, value })
+    })
+    .collect()
+}
+
+/// 把匹配结果编码为 JSON 数组
+pub fn matches_to_json(matches: &[Match], target: f64) -> String {
+    let objects: Vec<String> = matches.iter().map(|m| m.to_json(target)).collect();
+    format!("[{}]", objects.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let matches = solve(3.1, 1, 0.05);
+        assert!(matches.iter().all(|m| (m.value - 3.1).abs() < 0.05));
+        // 深度1时先生成单个常数，π 是第一个匹配结果
+        assert_eq!(
+            matches_to_json(&matches[..1], 3.1),
+            format!(
+                r#"[{{"rpn": "pi", "infix": "pi", "value": {}, "error": {}}}]"#,
+                std::f64::consts::PI,
+                std::f64::consts::PI - 3.1
+            )
+        );
+        assert!(solve(3.1, 0, 0.05).is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+// src/stats.rs
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use tf_solver::types::{Token, TokenVec};
+
+/// 目前为止与目标值最接近的表达式
+pub struct Candidate {
+    pub error: f64,         // 与目标值的绝对误差
+    pub tokens: Vec<Token>, // 表达式的token序列
+    pub value: f64,         // 表达式的值
+}
+
+impl Candidate {
+    /// 若 `value` 比当前候选更接近目标值（或还没有候选），替换为新的表达式
+    pub fn offer(best: &mut Option<Candidate>, tokens: &[Token], value: f64, target: f64) {
+        let error = (value - target).abs();
+        if !error.is_nan() && best.as_ref().is_none_or(|best| error < best.error) {
+            *best = Some(Candidate {
+                error,
+                tokens: tokens.to_vec(),
+                value,
+            });
+        }
+    }
+}
+
+// 误差直方图的桶数：误差小于 1e-12、[1e-12, 1e3) 中的每个数量级各一个、不小于 1e3
+const BUCKETS: usize = 17;
+const SMALLEST_EXPONENT: i32 = -12;
+
+/// 按数量级统计误差，工作线程在本地累加，处理完一个块后合并到 `SearchStats`
+#[derive(Default)]
+pub struct ErrorHistogram {
+    counts: [u64; BUCKETS],
+}
+
+impl ErrorHistogram {
+    /// 记录一个误差，`NaN`（超出定义域或中间结果过大）不计入
+    pub fn record(&mut self, error: f64) {
+        if error.is_nan() {
+            return;
+        }
+        let bucket = if error < 10f64.powi(SMALLEST_EXPONENT) {
+            0
+        } else if error < 10f64.powi(SMALLEST_EXPONENT + BUCKETS as i32 - 2) {
+            // 数量级边界附近 log10 的舍入可能差一位，这里不追求精确
+            let exponent = error.log10().floor() as i32;
+            (exponent - SMALLEST_EXPONENT + 1).clamp(1, BUCKETS as i32 - 2) as usize
+        } else {
+            BUCKETS - 1 // 包括无穷
+        };
+        self.counts[bucket] += 1;
+    }
+}
+
+/// 直方图第 `bucket` 个桶的区间
+fn bucket_label(bucket: usize) -> String {
+    let exponent = SMALLEST_EXPONENT + bucket as i32 - 1;
+    match bucket {
+        0 => format!("<1e{}", SMALLEST_EXPONENT),
+        _ if bucket == BUCKETS - 1 => format!(">=1e{}", exponent),
+        _ => format!("[1e{}, 1e{})", exponent, exponent + 1),
+    }
+}
+
+/// 搜索过程中各线程共享的进度统计
+#[derive(Default)]
+pub struct SearchStats {
+    pub examined: AtomicU64,            // 工作线程已检查的表达式数量
+    pub evaluated: AtomicU64,           // 通过 --must-use、--dedup-exact 筛选后实际计算的数量
+    pub matched: AtomicU64,             // 误差在容差内的表达式数量
+    pub partitions_done: AtomicUsize,   // 已生成完毕的划分数量
+    pub best: Mutex<Option<Candidate>>, // 目前为止最接近目标值的表达式，不论是否在容差内
+    histogram: [AtomicU64; BUCKETS],    // 所有已计算表达式的误差直方图
+}
+
+impl SearchStats {
+    /// 合并一个工作线程在某个块中找到的最佳候选
+    pub fn merge_best(&self, candidate: Option<Candidate>) {
+        let Some(candidate) = candidate else { return };
+        let mut best = self.best.lock().unwrap();
+        if best
+            .as_ref()
+            .is_none_or(|best| candidate.error < best.error)
+        {
+            *best = Some(candidate);
+        }
+    }
+
+    /// 合并一个工作线程在某个块中统计的误差直方图
+    pub fn merge_histogram(&self, histogram: &ErrorHistogram) {
+        for (total, &count) in self.histogram.iter().zip(&histogram.counts) {
+            if count > 0 {
+                total.fetch_add(count, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 把统计摘要输出到标准错误
+    pub fn print_summary(&self, total_partitions: usize) {
+        eprintln!(
+            "examined {} expressions, evaluated {}, matched {}, finished generating {}/{} partitions",
+            self.examined.load(Ordering::Relaxed),
+            self.evaluated.load(Ordering::Relaxed),
+            self.matched.load(Ordering::Relaxed),
+            self.partitions_done.load(Ordering::Relaxed),
+            total_partitions
+        );
+        if let Some(best) = self.best.lock().unwrap().as_ref() {
+            eprintln!(
+                "closest: {}: {} (error = {:e})",
+                TokenVec(&best.tokens),
+                best.value,
+                best.error
+            );
+        }
+        // 只列出非空的桶
+        let histogram: Vec<String> = self
+            .histogram
+            .iter()
+            .enumerate()
+            .filter_map(|(bucket, count)| {
+                let count = count.load(Ordering::Relaxed);
+                (count > 0).then(|| format!("{}: {}", bucket_label(bucket), count))
+            })
+            .collect();
+        if !histogram.is_empty() {
+            eprintln!("error histogram: {}", histogram.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_histogram() {
+        let mut histogram = ErrorHistogram::default();
+        for error in [
+            0.0,
+            1e-13,
+            5e-12,
+            0.5,
+            0.05,
+            0.09,
+            1e5,
+            f64::INFINITY,
+            f64::NAN,
+        ] {
+            histogram.record(error);
+        }
+        let stats = SearchStats::default();
+        stats.merge_histogram(&histogram);
+        stats.merge_histogram(&histogram);
+        let count = |label: &str| {
+            let bucket = (0..BUCKETS).find(|&b| bucket_label(b) == label).unwrap();
+            stats.histogram[bucket].load(Ordering::Relaxed)
+        };
+        assert_eq!(count("<1e-12"), 4);
+        assert_eq!(count("[1e-12, 1e-11)"), 2);
+        assert_eq!(count("[1e-1, 1e0)"), 2);
+        assert_eq!(count("[1e-2, 1e-1)"), 4);
+        assert_eq!(count(">=1e3"), 4);
+        assert_eq!(
+            stats
+                .histogram
+                .iter()
+                .map(|c| c.load(Ordering::Relaxed))
+                .sum::<u64>(),
+            16
+        );
+    }
+}
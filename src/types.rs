@@ -8,21 +8,36 @@ pub struct Operand {
     pub value: f64,     // 操作数的实际数值
 }
 
-// 定义运算符结构体，包含符号和对应的函数
+// 定义运算符结构体，包含符号、对应的函数以及可选的定义域
 #[derive(Clone)]
-pub struct Operator<T> {
-    pub symbol: String, // 运算符的符号表示
-    pub function: T,    // 运算符对应的函数
+pub struct Operator<T, D> {
+    pub symbol: String,    // 运算符的符号表示
+    pub function: T,       // 运算符对应的函数
+    pub domain: Option<D>, // 定义域判断函数，None 表示对任意输入都有效
 }
 
 // 定义一元运算符和二元运算符的类型别名
-pub type UnaryOperator = Operator<fn(f64) -> f64>; // 一元运算符：接收一个f64参数，返回f64
-pub type BinaryOperator = Operator<fn(f64, f64) -> f64>; // 二元运算符：接收两个f64参数，返回f64
+pub type UnaryOperator = UnaryOperatorOf<f64>; // 一元运算符：接收一个f64参数，返回f64
+pub type BinaryOperator = BinaryOperatorOf<f64>; // 二元运算符：接收两个f64参数，返回f64
+
+// 以任意数值类型R表示的运算符，用于高精度计算
+pub type UnaryOperatorOf<R> = Operator<fn(R) -> R, fn(R) -> bool>;
+pub type BinaryOperatorOf<R> = Operator<fn(R, R) -> R, fn(R, R) -> bool>;
 
 // 为Operator实现构造函数
-impl<T> Operator<T> {
+impl<T, D> Operator<T, D> {
     pub fn new(symbol: String, function: T) -> Self {
-        Self { symbol, function }
+        Self {
+            symbol,
+            function,
+            domain: None,
+        }
+    }
+
+    /// 设置运算符的定义域，超出定义域的输入在计算时会被丢弃
+    pub fn with_domain(mut self, domain: D) -> Self {
+        self.domain = Some(domain);
+        self
     }
 }
 
@@ -34,6 +49,17 @@ pub enum Token {
     BinaryOperator(BinaryOperator), // 二元运算符
 }
 
+impl Token {
+    /// 返回token的符号
+    pub fn symbol(&self) -> &str {
+        match self {
+            Token::Operand(operand) => &operand.symbol,
+            Token::UnaryOperator(operator) => &operator.symbol,
+            Token::BinaryOperator(operator) => &operator.symbol,
+        }
+    }
+}
+
 // 为Token实现Display trait，用于打印输出
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -0,0 +1,67 @@
+// src/ulp.rs
+
+/// 把 `f64` 的二进制表示映射为按数值单调递增的整数，相邻的浮点数映射为相邻的整数
+///
+/// 正数的二进制表示本身就是单调的；负数的符号位置1后按绝对值排列，需要取反，
+/// 因此 `0.0` 和 `-0.0` 都映射为0。
+fn ordered_bits(value: f64) -> i64 {
+    let bits = value.to_bits();
+    let magnitude = (bits & !(1 << 63)) as i64;
+    if bits >> 63 == 1 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// 两个浮点数之间相隔的可表示数值个数（ULP距离），任一个为 `NaN` 时返回 `None`
+///
+/// `0.0` 和 `-0.0` 的距离为0，符号不同的两个数的距离按经过0计算，
+/// 如最小的正次正规数与其相反数的距离为2。无穷大与 `f64::MAX` 的距离为1。
+pub fn ulp_distance(a: f64, b: f64) -> Option<u64> {
+    if a.is_nan() || b.is_nan() {
+        return None;
+    }
+    Some(ordered_bits(a).abs_diff(ordered_bits(b)))
+}
+
+/// 两个浮点数的ULP距离不超过 `max_ulps`，`NaN` 与任何值都不相等
+pub fn within_ulps(a: f64, b: f64, max_ulps: u64) -> bool {
+    ulp_distance(a, b).is_some_and(|distance| distance <= max_ulps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_rpn;
+    use crate::rpn::calculate;
+
+    #[test]
+    fn test_ulp_distance() {
+        let tiny = f64::from_bits(1); // 最小的正次正规数
+        assert_eq!(ulp_distance(1.0, 1.0), Some(0));
+        assert_eq!(ulp_distance(1.0, 1.0 + f64::EPSILON), Some(1));
+        assert_eq!(ulp_distance(1.0, 1.0 - f64::EPSILON / 2.0), Some(1));
+        assert_eq!(ulp_distance(-1.0, -1.0 - f64::EPSILON), Some(1));
+        // 符号位
+        assert_eq!(ulp_distance(0.0, -0.0), Some(0));
+        assert_eq!(ulp_distance(tiny, 0.0), Some(1));
+        assert_eq!(ulp_distance(tiny, -0.0), Some(1));
+        assert_eq!(ulp_distance(tiny, -tiny), Some(2));
+        assert_eq!(
+            ulp_distance(f64::MAX, f64::MIN),
+            Some(2 * f64::MAX.to_bits())
+        );
+        assert_eq!(ulp_distance(f64::MAX, f64::INFINITY), Some(1));
+        // NaN，包括符号位为1的 NaN
+        assert_eq!(ulp_distance(f64::NAN, f64::NAN), None);
+        assert_eq!(ulp_distance(1.0, -f64::NAN), None);
+        assert!(!within_ulps(f64::NAN, f64::NAN, u64::MAX));
+
+        // sqrt(pi)^2 在浮点误差内等于 pi，pi + 1e-9 则不是
+        let pi = std::f64::consts::PI;
+        let square = calculate(&parse_rpn("pi sqrt pi sqrt *").unwrap());
+        assert!(within_ulps(square, pi, 4));
+        assert!(!within_ulps(pi + 1e-9, pi, 4));
+    }
+}
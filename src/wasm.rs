@@ -0,0 +1,13 @@
+// src/wasm.rs
+use wasm_bindgen::prelude::*;
+
+use crate::solve::matches_to_json;
+
+/// 浏览器可调用的搜索入口，返回匹配结果组成的 JSON 数组字符串
+///
+/// 在当前线程中同步执行，`max_depth` 较大时会长时间阻塞页面，建议不超过3。
+#[wasm_bindgen]
+pub fn solve(target: f64, max_depth: usize, tolerance: f64) -> JsValue {
+    let matches = crate::solve::solve(target, max_depth, tolerance);
+    JsValue::from_str(&matches_to_json(&matches, target))
+}
@@ -0,0 +1,72 @@
+// tests/cli.rs
+//! 命令行程序的端到端测试：运行编译好的 tf_solver，检查标准输出中的匹配结果和标准错误中的统计
+#![cfg(feature = "native")]
+
+use std::process::Command;
+
+/// 以 CSV 格式运行一次搜索，返回 (标准输出, 标准错误)
+fn run(args: &[&str]) -> (String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_tf_solver"))
+        .args(["--format", "csv", "--num-threads", "1"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_min_tokens_only_suppresses_output() {
+    let pi = std::f64::consts::PI.to_string();
+    let search = [
+        "-t",
+        pi.as_str(),
+        "-d",
+        "1",
+        "-e",
+        "1e-9",
+        "--deterministic",
+    ];
+
+    let (stdout, stderr) = run(&search);
+    assert!(stdout.lines().any(|line| line.starts_with("\"pi\",")));
+
+    // 单个操作数 pi 恰好等于目标值：不再输出，但仍是最接近的表达式并计入统计
+    let (short_stdout, short_stderr) = run(&[&search[..], &["--min-tokens", "2"]].concat());
+    assert!(!short_stdout.lines().any(|line| line.starts_with("\"pi\",")));
+    assert!(
+        short_stdout
+            .lines()
+            .any(|line| line.starts_with("\"pi abs\","))
+    );
+    assert!(short_stderr.contains("closest: pi: "), "{}", short_stderr);
+    assert_eq!(short_stderr, stderr);
+}
+
+#[test]
+fn test_timeout_reports_reason() {
+    // 深度6的搜索远远无法在超时前完成
+    let (_, stderr) = run(&["-t", "1", "-d", "6", "--timeout", "0"]);
+    assert!(
+        stderr.starts_with("Timeout reached, search stopped early."),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_invalid_timeout_is_rejected() {
+    // 负数和 NaN 会使计时线程 panic，搜索随之失去时间上限，因此在解析参数时拒绝
+    for timeout in ["-1", "nan", "inf"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_tf_solver"))
+            .args(["-t", "1", &format!("--timeout={}", timeout)])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("invalid value"), "{}", stderr);
+    }
+}
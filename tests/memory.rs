@@ -0,0 +1,79 @@
+// tests/memory.rs
+//! 回调式生成器的峰值内存测试
+//!
+//! 用计数分配器记录堆内存的峰值。`visit_partition` 只在一个缓冲区上追加和回退token，
+//! 峰值应只与序列长度有关，与已遍历的序列数量无关；命令行程序在此基础上按
+//! `--chunk-size` 分块，经有界通道交给工作线程，因此整体内存占用也不随搜索深度增长。
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tf_solver::generator::{GenConfig, Partition, visit_partition};
+use tf_solver::opes_data::prepare_opes;
+
+/// 记录当前和峰值堆内存占用的分配器
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(current, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// 遍历长度为 `length` 的前 `limit` 个序列，返回遍历期间相对于开始时的堆内存峰值（字节）
+fn peak_while_visiting(length: usize, limit: usize) -> usize {
+    let (operands, unary_operators, binary_operators) = prepare_opes();
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    let mut visited = 0;
+    let flow = visit_partition(
+        &operands,
+        &unary_operators,
+        &binary_operators,
+        Partition::Length {
+            length,
+            first_operand: 0,
+        },
+        GenConfig::default(),
+        |tokens| {
+            assert_eq!(tokens.len(), length);
+            visited += 1;
+            if visited == limit {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        },
+    );
+    assert!(flow.is_break());
+    assert_eq!(visited, limit);
+    PEAK.load(Ordering::Relaxed) - baseline
+}
+
+#[test]
+fn test_visit_partition_peak_memory() {
+    // 长度9以上的空间无法穷举，这里只遍历前若干个序列
+    let short = peak_while_visiting(9, 10_000);
+    let long = peak_while_visiting(9, 1_000_000);
+    let deep = peak_while_visiting(30, 1_000_000);
+    // 每个token约几十字节，长度30的序列及其缓冲区远小于 64 KiB
+    assert!(long <= 64 * 1024, "peak {} bytes at length 9", long);
+    assert!(deep <= 64 * 1024, "peak {} bytes at length 30", deep);
+    // 遍历更多序列不会使峰值增长
+    assert_eq!(short, long);
+}